@@ -0,0 +1,264 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, ErrorKind, Read, Write},
+    iter
+};
+
+use crate::{chunked::ChunkedEncoder, header_map::HeaderMap};
+
+// how much of a streamed reader goes into one chunk
+const STREAM_CHUNK_SIZE: usize = 8192;
+
+// what a response sends after its head, files and readers are copied to the
+// stream as they are written instead of being loaded first
+pub enum Body {
+    Bytes(Vec<u8>),
+    Text(String),
+    File(File, u64),
+    Reader(Box<dyn Read + Send>, u64),
+    // the length is only known once the stream ends
+    Stream(BodyStream)
+}
+
+// chunks produced while the response is being written, e.g. a generated
+// report or the tail of a log
+pub struct BodyStream {
+    pub(crate) chunks: Box<dyn Iterator<Item = io::Result<Vec<u8>>> + Send>,
+    pub(crate) trailers: Option<Box<dyn FnOnce()->HeaderMap + Send>>
+}
+
+impl BodyStream {
+    pub fn new<I>(chunks: I)->Self
+    where
+        I: IntoIterator<Item = io::Result<Vec<u8>>>,
+        I::IntoIter: Send + 'static
+    {
+        BodyStream { chunks: Box::new(chunks.into_iter()), trailers: None }
+    }
+
+    // every read becomes a chunk, until the reader ends or fails
+    pub fn from_reader(mut reader: impl Read + Send + 'static)->Self{
+        let mut failed = false;
+        BodyStream::new(iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+            let mut chunk = vec![0; STREAM_CHUNK_SIZE];
+            loop {
+                match reader.read(&mut chunk) {
+                    Ok(0) => return None,
+                    Ok(size) => {
+                        chunk.truncate(size);
+                        return Some(Ok(chunk));
+                    },
+                    Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                    Err(e) => {
+                        failed = true;
+                        return Some(Err(e));
+                    }
+                }
+            }
+        }))
+    }
+
+    // called after the last chunk, so the fields can describe the whole body;
+    // only chunked responses carry trailers
+    pub fn trailers(mut self, trailers: impl FnOnce()->HeaderMap + Send + 'static)->Self{
+        self.trailers = Some(Box::new(trailers));
+        self
+    }
+}
+
+impl Body {
+    // the length comes from the file's metadata
+    pub fn from_file(file: File)->io::Result<Body>{
+        let len = file.metadata()?.len();
+        Ok(Body::File(file, len))
+    }
+
+    pub fn from_reader(reader: impl Read + Send + 'static, len: u64)->Body{
+        Body::Reader(Box::new(reader), len)
+    }
+
+    pub fn stream<I>(chunks: I)->Body
+    where
+        I: IntoIterator<Item = io::Result<Vec<u8>>>,
+        I::IntoIter: Send + 'static
+    {
+        Body::Stream(BodyStream::new(chunks))
+    }
+
+    // None for a stream, its length isn't known before it is sent
+    pub fn len(&self)->Option<u64>{
+        match self {
+            Body::Bytes(bytes) => Some(bytes.len() as u64),
+            Body::Text(text) => Some(text.len() as u64),
+            Body::File(_, len) | Body::Reader(_, len) => Some(*len),
+            Body::Stream(_) => None
+        }
+    }
+
+    pub fn is_empty(&self)->bool{
+        self.len() == Some(0)
+    }
+
+    // None for bodies that haven't been read yet
+    pub fn as_bytes(&self)->Option<&[u8]>{
+        match self {
+            Body::Bytes(bytes) => Some(bytes),
+            Body::Text(text) => Some(text.as_bytes()),
+            Body::File(..) | Body::Reader(..) | Body::Stream(_) => None
+        }
+    }
+
+    pub fn into_bytes(self)->io::Result<Vec<u8>>{
+        match self {
+            Body::Bytes(bytes) => Ok(bytes),
+            Body::Text(text) => Ok(text.into_bytes()),
+            body => {
+                let mut bytes = Vec::with_capacity(body.len().unwrap_or(0) as usize);
+                body.write_to(&mut bytes)?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    // writes exactly `len` bytes, a reader that ends early is an error since
+    // the client was promised that many by Content-Length. A stream is
+    // written as it is, the end of the connection marks the end of the body
+    pub fn write_to(self, stream: &mut impl Write)->io::Result<()>{
+        let (reader, len): (Box<dyn Read>, u64) = match self {
+            Body::Bytes(bytes) => return stream.write_all(&bytes),
+            Body::Text(text) => return stream.write_all(text.as_bytes()),
+            Body::File(file, len) => (Box::new(file), len),
+            Body::Reader(reader, len) => (reader, len),
+            Body::Stream(body_stream) => {
+                for chunk in body_stream.chunks {
+                    stream.write_all(&chunk?)?;
+                    stream.flush()?;
+                }
+                return Ok(());
+            }
+        };
+        let copied = io::copy(&mut reader.take(len), stream)?;
+        if copied < len {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "response body is shorter than its length"));
+        }
+
+        Ok(())
+    }
+
+    // Transfer-Encoding: chunked, with the stream's trailers after the last chunk
+    pub fn write_chunked(self, stream: &mut impl Write)->io::Result<()>{
+        let mut encoder = ChunkedEncoder::new(&mut *stream);
+        let trailers = match self {
+            Body::Stream(body_stream) => {
+                for chunk in body_stream.chunks {
+                    encoder.write_chunk(&chunk?)?;
+                }
+                body_stream.trailers.map(|trailers| trailers()).unwrap_or_default()
+            },
+            body => {
+                encoder.write_chunk(&body.into_bytes()?)?;
+                HeaderMap::new()
+            }
+        };
+        encoder.finish(&trailers)?;
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Body::Bytes(bytes) => f.debug_tuple("Bytes").field(&bytes.len()).finish(),
+            Body::Text(text) => f.debug_tuple("Text").field(text).finish(),
+            Body::File(file, len) => f.debug_tuple("File").field(file).field(len).finish(),
+            Body::Reader(_, len) => f.debug_tuple("Reader").field(len).finish(),
+            Body::Stream(_) => f.write_str("Stream")
+        }
+    }
+}
+
+impl From<BodyStream> for Body {
+    fn from(body_stream: BodyStream) -> Self {
+        Body::Stream(body_stream)
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Body::Bytes(bytes)
+    }
+}
+
+impl From<&[u8]> for Body {
+    fn from(bytes: &[u8]) -> Self {
+        Body::Bytes(bytes.to_vec())
+    }
+}
+
+impl From<String> for Body {
+    fn from(text: String) -> Self {
+        Body::Text(text)
+    }
+}
+
+impl From<&str> for Body {
+    fn from(text: &str) -> Self {
+        Body::Text(text.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn test_body_len(){
+        assert_eq!(Body::from("héllo").len(), Some(6));
+        assert_eq!(Body::from(vec![0, 1, 2]).len(), Some(3));
+        assert_eq!(Body::stream(Vec::new()).len(), None);
+        assert!(Body::from_reader(Cursor::new(Vec::new()), 0).is_empty());
+        assert_eq!(Body::from("abc").as_bytes(), Some(&b"abc"[..]));
+        assert_eq!(Body::from_reader(Cursor::new(b"abc"), 3).as_bytes(), None);
+    }
+    #[test]
+    fn test_write_reader_body(){
+        let mut output = Vec::new();
+        Body::from_reader(Cursor::new(b"0123456789"), 4).write_to(&mut output).unwrap();
+        assert_eq!(output, b"0123");
+
+        let error = Body::from_reader(Cursor::new(b"01"), 4).write_to(&mut Vec::new()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+    #[test]
+    fn test_file_body(){
+        let path = std::env::temp_dir().join(format!("http_body_test_{}", std::process::id()));
+        std::fs::write(&path, [0x89, b'P', b'N', b'G', 0, 255]).unwrap();
+        let body = Body::from_file(File::open(&path).unwrap()).unwrap();
+        assert_eq!(body.len(), Some(6));
+        assert_eq!(body.into_bytes().unwrap(), [0x89, b'P', b'N', b'G', 0, 255]);
+        std::fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn test_stream_body(){
+        let chunks = vec![Ok(b"hello".to_vec()), Ok(Vec::new()), Ok(b" world".to_vec())];
+        let body = BodyStream::new(chunks).trailers(|| [("X-Lines", "2")].into_iter().collect());
+        let mut output = Vec::new();
+        Body::from(body).write_chunked(&mut output).unwrap();
+        assert_eq!(output, b"5\r\nhello\r\n6\r\n world\r\n0\r\nX-Lines: 2\r\n\r\n");
+
+        let body = Body::from(BodyStream::from_reader(Cursor::new(vec![1; STREAM_CHUNK_SIZE + 1])));
+        assert_eq!(body.into_bytes().unwrap(), vec![1; STREAM_CHUNK_SIZE + 1]);
+
+        let chunks = vec![Ok(b"partial".to_vec()), Err(io::Error::other("report failed"))];
+        let mut output = Vec::new();
+        assert!(Body::stream(chunks).write_chunked(&mut output).is_err());
+        assert_eq!(output, b"7\r\npartial\r\n");
+    }
+}
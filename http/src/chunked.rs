@@ -0,0 +1,333 @@
+use std::io::{self, Write};
+
+use crate::{
+    header_map::HeaderMap,
+    http_request::{ParseError, is_tchar, parse_header_line},
+    request_parser::Limits
+};
+
+// longest chunk size a usize can hold in hex digits
+const MAX_CHUNK_SIZE_DIGITS: usize = 16;
+
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+enum ChunkState {
+    #[default]
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailers,
+    Done
+}
+
+// decodes a chunked body (RFC 9112 section 7.1) as its bytes arrive,
+// so a body split over many reads is walked only once
+#[derive(Debug, Default)]
+pub struct ChunkedDecoder {
+    state: ChunkState,
+    body: Vec<u8>,
+    trailers: HeaderMap,
+    trailers_size: usize
+}
+
+impl ChunkedDecoder {
+    pub fn new()->Self{
+        ChunkedDecoder::default()
+    }
+
+    pub fn is_done(&self)->bool{
+        self.state == ChunkState::Done
+    }
+
+    // consumes decoded bytes from the front of `buffer`, anything after
+    // the final CRLF is left for the next request
+    pub fn decode(&mut self, buffer: &mut Vec<u8>, limits: &Limits)->Result<(), ParseError>{
+        loop {
+            match self.state {
+                ChunkState::Size => {
+                    let Some(line) = take_line(buffer) else {
+                        if buffer.len() > limits.max_request_line {
+                            return Err(ParseError::InvalidChunk);
+                        }
+                        return Ok(())
+                    };
+                    let size = parse_chunk_size(&line)?;
+                    if size > limits.max_body_size.saturating_sub(self.body.len()) {
+                        return Err(ParseError::BodyTooLarge);
+                    }
+                    self.state = match size {
+                        0 => ChunkState::Trailers,
+                        size => ChunkState::Data(size)
+                    };
+                },
+                ChunkState::Data(remaining) => {
+                    if buffer.is_empty() {
+                        return Ok(());
+                    }
+                    let taken = remaining.min(buffer.len());
+                    self.body.extend(buffer.drain(..taken));
+                    self.state = match remaining - taken {
+                        0 => ChunkState::DataEnd,
+                        remaining => ChunkState::Data(remaining)
+                    };
+                },
+                ChunkState::DataEnd => {
+                    match buffer.as_slice() {
+                        [] | [b'\r'] => return Ok(()),
+                        [b'\r', b'\n', ..] => { buffer.drain(..2); },
+                        [b'\n', ..] => { buffer.drain(..1); },
+                        _ => return Err(ParseError::InvalidChunk)
+                    }
+                    self.state = ChunkState::Size;
+                },
+                ChunkState::Trailers => {
+                    let Some(line) = take_line(buffer) else {
+                        if self.trailers_size + buffer.len() > limits.max_header_size {
+                            return Err(ParseError::HeadersTooLarge);
+                        }
+                        return Ok(())
+                    };
+                    if line.is_empty() {
+                        self.state = ChunkState::Done;
+                        continue;
+                    }
+                    self.trailers_size += line.len();
+                    if self.trailers_size > limits.max_header_size {
+                        return Err(ParseError::HeadersTooLarge);
+                    }
+                    let (key, value) = parse_header_line(&line)?;
+                    self.trailers.append(key, value);
+                    if self.trailers.len() > limits.max_headers {
+                        return Err(ParseError::TooManyHeaders);
+                    }
+                },
+                ChunkState::Done => return Ok(())
+            }
+        }
+    }
+
+    pub fn into_parts(self)->(Vec<u8>, HeaderMap){
+        (self.body, self.trailers)
+    }
+}
+
+// frames a body of unknown length as chunks, the writer sees each chunk as
+// soon as it is written
+pub struct ChunkedEncoder<W: Write> {
+    writer: W
+}
+
+impl<W: Write> ChunkedEncoder<W> {
+    pub fn new(writer: W)->Self{
+        ChunkedEncoder { writer }
+    }
+
+    // an empty chunk would end the body, so it is skipped
+    pub fn write_chunk(&mut self, data: &[u8])->io::Result<()>{
+        if data.is_empty() {
+            return Ok(());
+        }
+        write!(self.writer, "{:x}\r\n", data.len())?;
+        self.writer.write_all(data)?;
+        self.writer.write_all(b"\r\n")?;
+        self.writer.flush()
+    }
+
+    // an invalid trailer fails before the last chunk, so the client sees an
+    // unfinished body rather than a forged field
+    pub fn finish(mut self, trailers: &HeaderMap)->io::Result<W>{
+        trailers.validate().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.writer.write_all(b"0\r\n")?;
+        for (key, value) in trailers.iter() {
+            write!(self.writer, "{key}: {value}\r\n")?;
+        }
+        self.writer.write_all(b"\r\n")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+// removes one line and its CRLF (or bare LF) from the buffer
+fn take_line(buffer: &mut Vec<u8>)->Option<Vec<u8>>{
+    let end = buffer.iter().position(|byte| *byte == b'\n')?;
+    let mut line: Vec<u8> = buffer.drain(..=end).collect();
+    line.pop();
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+
+    Some(line)
+}
+
+// chunk-size [ chunk-ext ], extensions are checked for syntax and ignored
+fn parse_chunk_size(line: &[u8])->Result<usize, ParseError>{
+    let line = std::str::from_utf8(line).map_err(|_| ParseError::InvalidChunk)?;
+    let (size, extensions) = match line.split_once(';') {
+        Some((size, extensions)) => (size, Some(extensions)),
+        None => (line, None)
+    };
+    let size = size.trim_end_matches([' ', '\t']);
+    if size.is_empty()
+        || size.len() > MAX_CHUNK_SIZE_DIGITS
+        || !size.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(ParseError::InvalidChunk);
+    }
+    if let Some(extensions) = extensions {
+        validate_chunk_extensions(extensions)?;
+    }
+
+    usize::from_str_radix(size, 16).map_err(|_| ParseError::InvalidChunk)
+}
+
+// chunk-ext = *( BWS ";" BWS ext-name [ BWS "=" BWS ext-val ] ), the first ";" is already consumed
+fn validate_chunk_extensions(extensions: &str)->Result<(), ParseError>{
+    let whitespace = [' ', '\t'];
+    let mut rest = extensions;
+    loop {
+        rest = rest.trim_start_matches(whitespace);
+        let name_len = token_len(rest);
+        if name_len == 0 {
+            return Err(ParseError::InvalidChunk);
+        }
+        rest = rest[name_len..].trim_start_matches(whitespace);
+        if let Some(value) = rest.strip_prefix('=') {
+            let value = value.trim_start_matches(whitespace);
+            rest = match value.strip_prefix('"') {
+                Some(quoted) => skip_quoted_string(quoted)?,
+                None => {
+                    let value_len = token_len(value);
+                    if value_len == 0 {
+                        return Err(ParseError::InvalidChunk);
+                    }
+                    &value[value_len..]
+                }
+            };
+            rest = rest.trim_start_matches(whitespace);
+        }
+        match rest.strip_prefix(';') {
+            Some(next) => rest = next,
+            None if rest.is_empty() => return Ok(()),
+            None => return Err(ParseError::InvalidChunk)
+        }
+    }
+}
+
+fn token_len(s: &str)->usize{
+    s.bytes().take_while(|b| is_tchar(*b)).count()
+}
+
+// returns what follows the closing quote
+fn skip_quoted_string(s: &str)->Result<&str, ParseError>{
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Ok(&s[i + 1..]),
+            _ => {}
+        }
+    }
+
+    Err(ParseError::InvalidChunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(data: &[u8])->Result<(ChunkedDecoder, Vec<u8>), ParseError>{
+        let mut decoder = ChunkedDecoder::new();
+        let mut buffer = data.to_vec();
+        decoder.decode(&mut buffer, &Limits::default())?;
+        Ok((decoder, buffer))
+    }
+
+    #[test]
+    fn test_decode_chunks(){
+        let (decoder, rest) = decode_all(b"5\r\nhello\r\n6\r\n world\r\n0\r\n\r\nGET").unwrap();
+        assert!(decoder.is_done());
+        assert_eq!(rest, b"GET");
+        let (body, trailers) = decoder.into_parts();
+        assert_eq!(body, b"hello world");
+        assert!(trailers.is_empty());
+    }
+    #[test]
+    fn test_decode_byte_by_byte(){
+        let data = b"A\r\n0123456789\r\n3;name=\"a;b\"\r\n\r\n\n\r\n0\r\nExpires: never\r\n\r\n";
+        let mut decoder = ChunkedDecoder::new();
+        let mut buffer = Vec::new();
+        for byte in data {
+            assert!(!decoder.is_done());
+            buffer.push(*byte);
+            decoder.decode(&mut buffer, &Limits::default()).unwrap();
+        }
+        assert!(decoder.is_done());
+        let (body, trailers) = decoder.into_parts();
+        assert_eq!(body, b"0123456789\r\n\n");
+        assert_eq!(trailers.get("expires"), Some("never"));
+    }
+    #[test]
+    fn test_chunk_extensions(){
+        let (decoder, _) = decode_all(b"3 ; foo ; bar=baz;q=\"x\\\"y\"\r\nabc\r\n0;last\r\n\r\n").unwrap();
+        assert!(decoder.is_done());
+        assert_eq!(decoder.into_parts().0, b"abc");
+    }
+    #[test]
+    fn test_trailers(){
+        let (decoder, _) = decode_all(b"1\r\na\r\n0\r\nDigest: sha-256=abc\r\nX-Count: 1\r\nx-count: 2\r\n\r\n").unwrap();
+        let (_, trailers) = decoder.into_parts();
+        assert_eq!(trailers.get("Digest"), Some("sha-256=abc"));
+        assert_eq!(trailers.get_all("X-Count"), vec!["1", "2"]);
+    }
+    #[test]
+    fn test_trailer_limits(){
+        let limits = Limits { max_headers: 1, max_header_size: 16, ..Limits::default() };
+        let mut decoder = ChunkedDecoder::new();
+        let mut buffer = b"0\r\nA: 1\r\nB: 2\r\n\r\n".to_vec();
+        assert_eq!(decoder.decode(&mut buffer, &limits), Err(ParseError::TooManyHeaders));
+
+        let mut decoder = ChunkedDecoder::new();
+        let mut buffer = b"0\r\nDigest: 0123456789abcdef".to_vec();
+        assert_eq!(decoder.decode(&mut buffer, &limits), Err(ParseError::HeadersTooLarge));
+    }
+    #[test]
+    fn test_encode_chunks(){
+        let mut encoder = ChunkedEncoder::new(Vec::new());
+        encoder.write_chunk(b"hello").unwrap();
+        encoder.write_chunk(b"").unwrap();
+        encoder.write_chunk(&[b'x'; 26]).unwrap();
+        let trailers: HeaderMap = [("Digest", "sha-256=abc")].into_iter().collect();
+        let encoded = encoder.finish(&trailers).unwrap();
+        assert!(encoded.starts_with(b"5\r\nhello\r\n1a\r\nxxx"));
+        assert!(encoded.ends_with(b"x\r\n0\r\nDigest: sha-256=abc\r\n\r\n"));
+
+        let (decoder, _) = decode_all(&encoded).unwrap();
+        let (body, trailers) = decoder.into_parts();
+        assert_eq!(body.len(), 31);
+        assert_eq!(trailers.get("digest"), Some("sha-256=abc"));
+    }
+    #[test]
+    fn test_encode_invalid_trailers(){
+        for (name, value) in [("Digest", "abc\r\n\r\nHTTP/1.1 200 OK"), ("Bad Name", "1")] {
+            let mut trailers = HeaderMap::new();
+            trailers.append(name, value);
+            let mut encoder = ChunkedEncoder::new(Vec::new());
+            encoder.write_chunk(b"hello").unwrap();
+            let error = encoder.finish(&trailers).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+    #[test]
+    fn test_invalid_chunks(){
+        let cases: [&[u8]; 6] = [
+            b"z\r\n",
+            b"\r\n",
+            b"3\r\nabcd\r\n",
+            b"3;=x\r\nabc\r\n",
+            b"3;a=\"open\r\nabc\r\n",
+            b"11111111111111111\r\n",
+        ];
+        for data in cases {
+            assert_eq!(decode_all(data).unwrap_err(), ParseError::InvalidChunk, "{}", String::from_utf8_lossy(data));
+        }
+    }
+}
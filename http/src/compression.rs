@@ -0,0 +1,326 @@
+use std::{io::{self, Read, Write}, iter};
+
+use brotli::CompressorWriter;
+use flate2::{write::{GzEncoder, ZlibEncoder}, Compression as Level};
+
+use crate::{
+    body::{Body, BodyStream},
+    http_request::HttpRequest,
+    http_response::HttpResponse,
+    status_code::StatusCode,
+    typed_headers::{AcceptEncoding, ContentType}
+};
+
+// brotli's highest qualities are too slow to run on every response
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+const BROTLI_BUFFER_SIZE: usize = 4096;
+
+// which responses are worth compressing, like Limits it is set once per server
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Compression {
+    // bodies smaller than this gain less than the Content-Encoding header costs
+    pub min_size: u64,
+    // media types, `type/*` matches a whole type
+    pub mime_types: &'static [&'static str]
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Compression {
+            min_size: 1024,
+            mime_types: &[
+                "text/*",
+                "application/javascript",
+                "application/json",
+                "application/xml",
+                "application/wasm",
+                "image/svg+xml"
+            ]
+        }
+    }
+}
+
+impl Compression {
+    pub fn allows(&self, media_type: &str)->bool{
+        self.mime_types.iter().any(|allowed| match allowed.strip_suffix("/*") {
+            Some(main_type) => media_type
+                .split_once('/')
+                .is_some_and(|(media_main_type, _)| media_main_type.eq_ignore_ascii_case(main_type)),
+            None => allowed.eq_ignore_ascii_case(media_type)
+        })
+    }
+}
+
+// the content codings this server can produce, `deflate` is the zlib format (RFC 9110 section 8.4.1)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ContentCoding {
+    Brotli,
+    Gzip,
+    Deflate
+}
+
+impl ContentCoding {
+    // on equal q-values the first one wins
+    const ALL: [ContentCoding; 3] = [ContentCoding::Brotli, ContentCoding::Gzip, ContentCoding::Deflate];
+
+    pub fn as_str(&self)->&'static str{
+        match self {
+            ContentCoding::Brotli => "br",
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate"
+        }
+    }
+}
+
+impl HttpRequest {
+    // None without Accept-Encoding or when the client ranks identity higher
+    pub fn preferred_coding(&self)->Option<ContentCoding>{
+        let accept_encoding = self.typed_header::<AcceptEncoding>()?;
+        let available = ContentCoding::ALL.map(|coding| coding.as_str());
+        let preferred = accept_encoding.preferred(&available)?;
+        // identity is acceptable by default, but only a fallback unless it is listed
+        let identity = accept_encoding.items().iter().find(|item| item.value == "identity");
+        if identity.is_some_and(|identity| identity.quality > accept_encoding.quality(preferred)) {
+            return None;
+        }
+        ContentCoding::ALL.into_iter().find(|coding| coding.as_str() == preferred)
+    }
+}
+
+impl HttpResponse {
+    // encodes the body with the coding `req` prefers, if its type and size are
+    // worth it; buffered bodies stay buffered and everything else is streamed.
+    // A 304 still carrying the 200's headers and body gets the Vary and ETag
+    // that 200 would have had, its body is never encoded
+    pub fn compress(&mut self, req: &HttpRequest, compression: &Compression)->io::Result<()>{
+        let not_modified = self.status() == StatusCode::NOT_MODIFIED;
+        // a range is a slice of the unencoded representation
+        if (!self.status().allows_body() && !not_modified) || self.status() == StatusCode::PARTIAL_CONTENT {
+            return Ok(());
+        }
+        if self.headers().contains_key("Content-Encoding") {
+            return Ok(());
+        }
+        let compressible = self
+            .typed_header::<ContentType>()
+            .is_some_and(|content_type| compression.allows(content_type.media_type()));
+        let large_enough = self.body().is_some_and(|body| body.len().is_none_or(|len| len >= compression.min_size));
+        if !compressible || !large_enough {
+            return Ok(());
+        }
+        // the same URL is now sent differently depending on Accept-Encoding
+        let headers = self.headers();
+        if !headers.contains_token("Vary", "*") && !headers.contains_token("Vary", "Accept-Encoding") {
+            self.headers_mut().append("Vary", "Accept-Encoding");
+        }
+
+        let Some(coding) = req.preferred_coding() else {
+            return Ok(());
+        };
+        if not_modified {
+            self.weaken_etag();
+            return Ok(());
+        }
+        let Some(body) = self.take_body() else {
+            return Ok(());
+        };
+        let encoded = match body {
+            Body::Bytes(_) | Body::Text(_) => {
+                let mut encoder = Encoder::new(coding);
+                let mut encoded = encoder.write(body.as_bytes().unwrap_or_default())?;
+                encoded.extend(encoder.finish()?);
+                Body::Bytes(encoded)
+            },
+            Body::File(file, len) => Body::Stream(encode_stream(BodyStream::from_reader(file.take(len)), coding)),
+            Body::Reader(reader, len) => Body::Stream(encode_stream(BodyStream::from_reader(reader.take(len)), coding)),
+            Body::Stream(body_stream) => Body::Stream(encode_stream(body_stream, coding))
+        };
+        self.set_body(encoded);
+        self.headers_mut().insert("Content-Encoding", coding.as_str());
+        self.weaken_etag();
+
+        Ok(())
+    }
+
+    // validators describe the unencoded bytes, a weak ETag still matches them
+    fn weaken_etag(&mut self){
+        if let Some(etag) = self.headers().get("ETag").filter(|etag| !etag.starts_with("W/")) {
+            let weak = format!("W/{etag}");
+            self.headers_mut().insert("ETag", weak);
+        }
+    }
+}
+
+// every chunk is flushed through the encoder, so a slow stream still reaches
+// the client as it is produced
+fn encode_stream(body_stream: BodyStream, coding: ContentCoding)->BodyStream{
+    let BodyStream { mut chunks, trailers } = body_stream;
+    let mut encoder = Some(Encoder::new(coding));
+    let encoded = iter::from_fn(move || loop {
+        let current = encoder.as_mut()?;
+        match chunks.next() {
+            Some(Ok(chunk)) => match current.write(&chunk) {
+                Ok(output) if output.is_empty() => continue,
+                result => return Some(result)
+            },
+            Some(Err(e)) => {
+                encoder = None;
+                return Some(Err(e));
+            },
+            None => return encoder.take().map(Encoder::finish)
+        }
+    });
+    BodyStream { chunks: Box::new(encoded), trailers }
+}
+
+enum Encoder {
+    Brotli(Box<CompressorWriter<Vec<u8>>>),
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(ZlibEncoder<Vec<u8>>)
+}
+
+impl Encoder {
+    fn new(coding: ContentCoding)->Self{
+        match coding {
+            ContentCoding::Brotli => Encoder::Brotli(Box::new(
+                CompressorWriter::new(Vec::new(), BROTLI_BUFFER_SIZE, BROTLI_QUALITY, BROTLI_WINDOW)
+            )),
+            ContentCoding::Gzip => Encoder::Gzip(GzEncoder::new(Vec::new(), Level::default())),
+            ContentCoding::Deflate => Encoder::Deflate(ZlibEncoder::new(Vec::new(), Level::default()))
+        }
+    }
+
+    // returns what the encoder has produced so far
+    fn write(&mut self, data: &[u8])->io::Result<Vec<u8>>{
+        let output = match self {
+            Encoder::Brotli(encoder) => {
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            },
+            Encoder::Gzip(encoder) => {
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            },
+            Encoder::Deflate(encoder) => {
+                encoder.write_all(data)?;
+                encoder.flush()?;
+                encoder.get_mut()
+            }
+        };
+        Ok(std::mem::take(output))
+    }
+
+    fn finish(self)->io::Result<Vec<u8>>{
+        match self {
+            Encoder::Brotli(encoder) => Ok(encoder.into_inner()),
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Deflate(encoder) => encoder.finish()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use flate2::read::{GzDecoder, ZlibDecoder};
+
+    use super::*;
+
+    fn response(body: impl Into<Body>)->HttpResponse{
+        HttpResponse::builder()
+            .header("Content-Type", "text/javascript")
+            .header("ETag", "\"v1\"")
+            .body(body)
+    }
+
+    fn decode(coding: ContentCoding, encoded: &[u8])->Vec<u8>{
+        let mut decoded = Vec::new();
+        match coding {
+            ContentCoding::Brotli => brotli::Decompressor::new(encoded, 4096).read_to_end(&mut decoded),
+            ContentCoding::Gzip => GzDecoder::new(encoded).read_to_end(&mut decoded),
+            ContentCoding::Deflate => ZlibDecoder::new(encoded).read_to_end(&mut decoded)
+        }.unwrap();
+        decoded
+    }
+
+    #[test]
+    fn test_preferred_coding(){
+        assert_eq!(HttpRequest::test_request("GET", "/app.js", "Accept-Encoding: gzip, deflate, br\r\n", b"").preferred_coding(), Some(ContentCoding::Brotli));
+        assert_eq!(HttpRequest::test_request("GET", "/app.js", "Accept-Encoding: gzip;q=0.8, br;q=0.5\r\n", b"").preferred_coding(), Some(ContentCoding::Gzip));
+        assert_eq!(HttpRequest::test_request("GET", "/app.js", "Accept-Encoding: deflate\r\n", b"").preferred_coding(), Some(ContentCoding::Deflate));
+        assert_eq!(HttpRequest::test_request("GET", "/app.js", "Accept-Encoding: gzip;q=0.5\r\n", b"").preferred_coding(), Some(ContentCoding::Gzip));
+        assert_eq!(HttpRequest::test_request("GET", "/app.js", "Accept-Encoding: gzip;q=0.5, identity\r\n", b"").preferred_coding(), None);
+        assert_eq!(HttpRequest::test_request("GET", "/app.js", "Accept-Encoding: *\r\n", b"").preferred_coding(), Some(ContentCoding::Brotli));
+        assert_eq!(HttpRequest::test_request("GET", "/app.js", "Accept-Encoding: zstd\r\n", b"").preferred_coding(), None);
+        assert_eq!(HttpRequest::test_request("GET", "/", "", b"").preferred_coding(), None);
+    }
+    #[test]
+    fn test_compression_allows(){
+        let compression = Compression::default();
+        assert!(compression.allows("text/css"));
+        assert!(compression.allows("image/svg+xml"));
+        assert!(!compression.allows("image/png"));
+        assert!(!compression.allows("textual/plain"));
+    }
+    #[test]
+    fn test_compress_buffered_body(){
+        let script = "console.log('hello');\n".repeat(100);
+        for coding in ContentCoding::ALL {
+            let mut response = response(script.clone());
+            response.compress(&HttpRequest::test_request("GET", "/app.js", &format!("Accept-Encoding: {}\r\n", coding.as_str()), b""), &Compression::default()).unwrap();
+            assert_eq!(response.headers().get("Content-Encoding"), Some(coding.as_str()));
+            assert_eq!(response.headers().get("Vary"), Some("Accept-Encoding"));
+            assert_eq!(response.headers().get("ETag"), Some("W/\"v1\""));
+
+            let encoded = response.body().and_then(Body::as_bytes).unwrap();
+            assert!(encoded.len() < script.len() / 10, "{coding:?}");
+            assert_eq!(decode(coding, encoded), script.as_bytes());
+        }
+    }
+    #[test]
+    fn test_compress_skips(){
+        let compression = Compression::default();
+        let mut small = response("let a = 1;");
+        small.compress(&HttpRequest::test_request("GET", "/app.js", "Accept-Encoding: gzip\r\n", b""), &compression).unwrap();
+        assert!(!small.headers().contains_key("Content-Encoding"));
+        assert!(!small.headers().contains_key("Vary"));
+
+        let mut identity = response("x".repeat(2000));
+        identity.compress(&HttpRequest::test_request("GET", "/app.js", "Accept-Encoding: identity\r\n", b""), &compression).unwrap();
+        assert!(!identity.headers().contains_key("Content-Encoding"));
+        assert_eq!(identity.headers().get("Vary"), Some("Accept-Encoding"));
+
+        let mut image = HttpResponse::builder().header("Content-Type", "image/png").body(vec![0; 2000]);
+        image.compress(&HttpRequest::test_request("GET", "/app.js", "Accept-Encoding: gzip\r\n", b""), &compression).unwrap();
+        assert!(!image.headers().contains_key("Content-Encoding"));
+    }
+    #[test]
+    fn test_compress_not_modified(){
+        let script = "console.log('hello');\n".repeat(100);
+        let mut not_modified = response(script.clone());
+        not_modified.set_status(StatusCode::NOT_MODIFIED);
+        not_modified.compress(&HttpRequest::test_request("GET", "/app.js", "Accept-Encoding: gzip\r\n", b""), &Compression::default()).unwrap();
+        assert_eq!(not_modified.headers().get("Vary"), Some("Accept-Encoding"));
+        assert_eq!(not_modified.headers().get("ETag"), Some("W/\"v1\""));
+        assert!(!not_modified.headers().contains_key("Content-Encoding"));
+        assert_eq!(not_modified.body().and_then(Body::as_bytes), Some(script.as_bytes()));
+    }
+    #[test]
+    fn test_compress_streamed_body(){
+        let report = "row,value\n".repeat(500);
+        let mut response = response(Body::from_reader(Cursor::new(report.clone().into_bytes()), report.len() as u64));
+        response.compress(&HttpRequest::test_request("GET", "/app.js", "Accept-Encoding: gzip\r\n", b""), &Compression::default()).unwrap();
+        assert_eq!(response.body().and_then(Body::len), None);
+
+        let mut output = Vec::new();
+        response.send_response(&mut output).unwrap();
+        let output = String::from_utf8_lossy(&output);
+        assert!(output.contains("Content-Encoding: gzip\r\n"));
+        assert!(output.contains("Transfer-Encoding: chunked\r\n"));
+        assert!(!output.contains("Content-Length"));
+    }
+}
@@ -0,0 +1,130 @@
+use std::time::SystemTime;
+
+use crate::{
+    header_map::HeaderMap,
+    http_date::unix_secs,
+    http_request::{HttpRequest, Method},
+    http_response::HttpResponse,
+    status_code::StatusCode,
+    typed_headers::{EntityTag, IfMatch, IfModifiedSince, IfNoneMatch, IfUnmodifiedSince}
+};
+
+// headers a 304 keeps from the 200 it stands for (RFC 9110 section 15.4.5)
+const NOT_MODIFIED_HEADERS: [&str; 7] = ["Cache-Control", "Content-Location", "Date", "ETag", "Expires", "Last-Modified", "Vary"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Precondition {
+    Proceed,
+    NotModified,
+    Failed
+}
+
+impl HttpRequest {
+    // RFC 9110 section 13.2.2, for a representation that exists with these validators
+    pub fn evaluate_preconditions(&self, etag: Option<&EntityTag>, last_modified: Option<SystemTime>)->Precondition{
+        let is_get_or_head = matches!(self.method, Method::Get | Method::Head);
+
+        if let Some(if_match) = self.typed_header::<IfMatch>() {
+            let matches = match if_match {
+                IfMatch::Any => true,
+                tags => etag.is_some_and(|etag| tags.matches(etag))
+            };
+            if !matches {
+                return Precondition::Failed;
+            }
+        } else if let Some(IfUnmodifiedSince(date)) = self.typed_header() {
+            if last_modified.is_some_and(|modified| unix_secs(modified) > unix_secs(date)) {
+                return Precondition::Failed;
+            }
+        }
+
+        if let Some(if_none_match) = self.typed_header::<IfNoneMatch>() {
+            let matches = match if_none_match {
+                IfNoneMatch::Any => true,
+                tags => etag.is_some_and(|etag| tags.matches(etag))
+            };
+            if matches {
+                return match is_get_or_head {
+                    true => Precondition::NotModified,
+                    false => Precondition::Failed
+                };
+            }
+        } else if let Some(IfModifiedSince(date)) = self.typed_header() {
+            if is_get_or_head && last_modified.is_some_and(|modified| unix_secs(modified) <= unix_secs(date)) {
+                return Precondition::NotModified;
+            }
+        }
+
+        Precondition::Proceed
+    }
+}
+
+impl HttpResponse {
+    // `headers` are the ones the 200 would have had
+    pub fn not_modified(headers: &HeaderMap)->Self{
+        let headers = headers
+            .iter()
+            .filter(|(key, _)| NOT_MODIFIED_HEADERS.iter().any(|kept| kept.eq_ignore_ascii_case(key)))
+            .collect();
+        HttpResponse::new(StatusCode::NOT_MODIFIED, Some(headers), None)
+    }
+
+    pub fn precondition_failed()->Self{
+        HttpResponse::new(StatusCode::PRECONDITION_FAILED, Some(HeaderMap::new()), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    #[test]
+    fn test_if_none_match(){
+        let etag = EntityTag::strong("v1");
+        let req = HttpRequest::test_request("GET", "/page", "If-None-Match: \"v0\", W/\"v1\"\r\n", b"");
+        assert_eq!(req.evaluate_preconditions(Some(&etag), None), Precondition::NotModified);
+        assert_eq!(req.evaluate_preconditions(Some(&EntityTag::strong("v2")), None), Precondition::Proceed);
+
+        let req = HttpRequest::test_request("PUT", "/page", "If-None-Match: *\r\n", b"");
+        assert_eq!(req.evaluate_preconditions(Some(&etag), None), Precondition::Failed);
+    }
+    #[test]
+    fn test_if_modified_since(){
+        let modified = UNIX_EPOCH + Duration::new(784111777, 500_000_000);
+        let req = HttpRequest::test_request("GET", "/page", "If-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT\r\n", b"");
+        assert_eq!(req.evaluate_preconditions(None, Some(modified)), Precondition::NotModified);
+        assert_eq!(req.evaluate_preconditions(None, Some(modified + Duration::from_secs(1))), Precondition::Proceed);
+
+        // If-None-Match takes precedence
+        let req = HttpRequest::test_request("GET", "/page", "If-None-Match: \"v2\"\r\nIf-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT\r\n", b"");
+        assert_eq!(req.evaluate_preconditions(Some(&EntityTag::strong("v1")), Some(modified)), Precondition::Proceed);
+
+        let req = HttpRequest::test_request("GET", "/page", "If-Modified-Since: yesterday\r\n", b"");
+        assert_eq!(req.evaluate_preconditions(None, Some(modified)), Precondition::Proceed);
+    }
+    #[test]
+    fn test_if_match_and_if_unmodified_since(){
+        let etag = EntityTag::strong("v1");
+        assert_eq!(HttpRequest::test_request("GET", "/page", "If-Match: \"v1\"\r\n", b"").evaluate_preconditions(Some(&etag), None), Precondition::Proceed);
+        assert_eq!(HttpRequest::test_request("GET", "/page", "If-Match: W/\"v1\"\r\n", b"").evaluate_preconditions(Some(&etag), None), Precondition::Failed);
+        assert_eq!(HttpRequest::test_request("GET", "/page", "If-Match: *\r\n", b"").evaluate_preconditions(None, None), Precondition::Proceed);
+
+        let modified = UNIX_EPOCH + Duration::from_secs(784111778);
+        let req = HttpRequest::test_request("GET", "/page", "If-Unmodified-Since: Sun, 06 Nov 1994 08:49:37 GMT\r\n", b"");
+        assert_eq!(req.evaluate_preconditions(None, Some(modified)), Precondition::Failed);
+        let req = HttpRequest::test_request("GET", "/page", "If-Match: \"v1\"\r\nIf-Unmodified-Since: Sun, 06 Nov 1994 08:49:37 GMT\r\n", b"");
+        assert_eq!(req.evaluate_preconditions(Some(&etag), Some(modified)), Precondition::Proceed);
+    }
+    #[test]
+    fn test_not_modified_response(){
+        let headers: HeaderMap = [("Content-Type", "text/html"), ("ETag", "\"v1\""), ("Cache-Control", "no-cache")].into_iter().collect();
+        let mut output = Vec::new();
+        HttpResponse::not_modified(&headers).send_response(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "HTTP/1.1 304 Not Modified\r\nETag: \"v1\"\r\nCache-Control: no-cache\r\n\r\n"
+        );
+    }
+}
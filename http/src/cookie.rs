@@ -0,0 +1,286 @@
+use std::{error::Error, fmt::{self, Display}, time::{Duration, SystemTime}};
+
+use crate::{http_date::format_http_date, http_request::{HttpRequest, is_token}};
+
+// cookies a client sent, in the order they appeared
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CookieJar {
+    cookies: Vec<(String, String)>
+}
+
+impl CookieJar {
+    pub fn get(&self, name: &str)->Option<&str>{
+        self.cookies
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn iter(&self)->impl Iterator<Item = (&str, &str)>{
+        self.cookies.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self)->usize{
+        self.cookies.len()
+    }
+
+    pub fn is_empty(&self)->bool{
+        self.cookies.is_empty()
+    }
+}
+
+impl From<&str> for CookieJar {
+    // `Cookie: a=1; b="2"`, pairs without a name are skipped
+    fn from(header: &str) -> Self {
+        let cookies = header
+            .split(';')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .filter(|(key, _)| !key.is_empty())
+            .map(|(key, value)| {
+                let value = value
+                    .strip_prefix('"')
+                    .and_then(|value| value.strip_suffix('"'))
+                    .unwrap_or(value);
+                (key.to_string(), value.to_string())
+            })
+            .collect();
+
+        CookieJar { cookies }
+    }
+}
+
+impl HttpRequest {
+    pub fn cookies(&self)->CookieJar{
+        let mut jar = CookieJar::default();
+        for header in self.headers.get_all("Cookie") {
+            jar.cookies.extend(CookieJar::from(header).cookies);
+        }
+
+        jar
+    }
+}
+
+// a part of a cookie that would change the meaning of its Set-Cookie field,
+// `field` is "name", "value", "domain" or "path"
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidCookie {
+    pub field: &'static str,
+    pub value: String
+}
+
+impl Display for InvalidCookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid cookie {} {:?}", self.field, self.value)
+    }
+}
+
+impl Error for InvalidCookie {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None
+}
+
+// a Set-Cookie value, see RFC 6265 section 4.1
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    expires: Option<SystemTime>,
+    max_age: Option<u64>,
+    domain: Option<String>,
+    path: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>
+}
+
+impl Cookie {
+    // the name is a token and the value cookie-octets, optionally quoted
+    pub fn new(name: impl Into<String>, value: impl Into<String>)->Result<Self, InvalidCookie>{
+        let (name, value) = (name.into(), value.into());
+        if !is_token(&name) {
+            return Err(InvalidCookie { field: "name", value: name });
+        }
+        let unquoted = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(&value);
+        if !unquoted.bytes().all(is_cookie_octet) {
+            return Err(InvalidCookie { field: "value", value });
+        }
+
+        Ok(Cookie {
+            name,
+            value,
+            expires: None,
+            max_age: None,
+            domain: None,
+            path: None,
+            secure: false,
+            http_only: false,
+            same_site: None
+        })
+    }
+
+    // tells the client to delete its cookie with this name
+    pub fn removal(name: impl Into<String>)->Result<Self, InvalidCookie>{
+        let cookie = Cookie::new(name, "")?
+            .max_age(Duration::ZERO)
+            .expires(SystemTime::UNIX_EPOCH);
+        Ok(cookie)
+    }
+
+    pub fn expires(mut self, expires: SystemTime)->Self{
+        self.expires = Some(expires);
+        self
+    }
+
+    pub fn max_age(mut self, max_age: Duration)->Self{
+        self.max_age = Some(max_age.as_secs());
+        self
+    }
+
+    pub fn domain(mut self, domain: impl Into<String>)->Result<Self, InvalidCookie>{
+        self.domain = Some(check_attribute("domain", domain.into())?);
+        Ok(self)
+    }
+
+    pub fn path(mut self, path: impl Into<String>)->Result<Self, InvalidCookie>{
+        self.path = Some(check_attribute("path", path.into())?);
+        Ok(self)
+    }
+
+    pub fn secure(mut self, secure: bool)->Self{
+        self.secure = secure;
+        self
+    }
+
+    pub fn http_only(mut self, http_only: bool)->Self{
+        self.http_only = http_only;
+        self
+    }
+
+    // browsers drop `SameSite=None` cookies that aren't Secure
+    pub fn same_site(mut self, same_site: SameSite)->Self{
+        self.same_site = Some(same_site);
+        if same_site == SameSite::None {
+            self.secure = true;
+        }
+        self
+    }
+
+    pub fn name(&self)->&str{
+        &self.name
+    }
+
+    pub fn value(&self)->&str{
+        &self.value
+    }
+}
+
+// any US-ASCII except CTLs, whitespace, DQUOTE, comma, semicolon and backslash
+fn is_cookie_octet(byte: u8)->bool{
+    matches!(byte, 0x21 | 0x23..=0x2b | 0x2d..=0x3a | 0x3c..=0x5b | 0x5d..=0x7e)
+}
+
+// an attribute value ends at `;`, and a CTL could end the whole field
+fn check_attribute(field: &'static str, value: String)->Result<String, InvalidCookie>{
+    match value.bytes().any(|byte| byte == b';' || byte.is_ascii_control()) {
+        true => Err(InvalidCookie { field, value }),
+        false => Ok(value)
+    }
+}
+
+impl Display for Cookie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.name, self.value)?;
+        if let Some(expires) = self.expires {
+            write!(f, "; Expires={}", format_http_date(expires))?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(f, "; Max-Age={max_age}")?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(f, "; Domain={domain}")?;
+        }
+        if let Some(path) = &self.path {
+            write!(f, "; Path={path}")?;
+        }
+        if self.secure {
+            write!(f, "; Secure")?;
+        }
+        if self.http_only {
+            write!(f, "; HttpOnly")?;
+        }
+        match self.same_site {
+            Some(SameSite::Strict) => write!(f, "; SameSite=Strict"),
+            Some(SameSite::Lax) => write!(f, "; SameSite=Lax"),
+            Some(SameSite::None) => write!(f, "; SameSite=None"),
+            None => Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+
+    #[test]
+    fn test_request_cookies(){
+        let req = HttpRequest::parse(b"GET / HTTP/1.1\r\nCookie: session=abc; theme=\"dark\"\r\nCookie: lang=hr;;broken\r\n\r\n").unwrap();
+        let jar = req.cookies();
+        assert_eq!(jar.get("session"), Some("abc"));
+        assert_eq!(jar.get("theme"), Some("dark"));
+        assert_eq!(jar.get("lang"), Some("hr"));
+        assert_eq!(jar.get("broken"), None);
+        assert_eq!(jar.len(), 3);
+    }
+    #[test]
+    fn test_set_cookie_attributes(){
+        let cookie = Cookie::new("session", "abc").unwrap()
+            .expires(UNIX_EPOCH + Duration::from_secs(784111777))
+            .max_age(Duration::from_secs(3600))
+            .domain("example.com").unwrap()
+            .path("/app").unwrap()
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::Lax);
+        assert_eq!(
+            cookie.to_string(),
+            "session=abc; Expires=Sun, 06 Nov 1994 08:49:37 GMT; Max-Age=3600; Domain=example.com; Path=/app; Secure; HttpOnly; SameSite=Lax"
+        );
+    }
+    #[test]
+    fn test_same_site_none_is_secure(){
+        let cookie = Cookie::new("id", "1").unwrap().same_site(SameSite::None);
+        assert_eq!(cookie.to_string(), "id=1; Secure; SameSite=None");
+    }
+    #[test]
+    fn test_removal_cookie(){
+        assert_eq!(
+            Cookie::removal("session").unwrap().to_string(),
+            "session=; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Max-Age=0"
+        );
+    }
+    #[test]
+    fn test_invalid_cookies(){
+        assert_eq!(Cookie::new("theme", "\"dark\"").unwrap().to_string(), "theme=\"dark\"");
+        assert_eq!(
+            Cookie::new("id", "1; Domain=evil.example").unwrap_err(),
+            InvalidCookie { field: "value", value: "1; Domain=evil.example".to_string() }
+        );
+        for (name, value) in [("a b", "1"), ("", "1"), ("a=b", "1"), ("id", "a b"), ("id", "a,b"), ("id", "a\\b"), ("id", "\"a"), ("id", "1\r\nX: 2")] {
+            assert!(Cookie::new(name, value).is_err(), "{name}={value}");
+        }
+        let cookie = Cookie::new("id", "1").unwrap();
+        assert_eq!(cookie.clone().domain("example.com; Secure").unwrap_err().field, "domain");
+        assert_eq!(cookie.clone().path("/a\nb").unwrap_err().field, "path");
+        assert!(cookie.path("/a b").is_ok());
+    }
+}
@@ -0,0 +1,290 @@
+use std::{error::Error, fmt::{self, Display}};
+
+use crate::{
+    header_map::HeaderMap,
+    http_request::{HttpRequest, parse_header_line},
+    typed_headers::{ContentType, split_parameters},
+    uri::{QueryMap, parse_query}
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FormLimits {
+    // text fields and files together
+    pub max_fields: usize,
+    pub max_field_size: usize,
+    pub max_file_size: usize
+}
+
+impl Default for FormLimits {
+    fn default() -> Self {
+        FormLimits {
+            max_fields: 1000,
+            max_field_size: 64 * 1024,
+            max_file_size: 10 * 1024 * 1024
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FilePart {
+    pub name: String,
+    pub filename: String,
+    pub content_type: Option<String>,
+    pub headers: HeaderMap,
+    pub data: Vec<u8>
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FormData {
+    fields: QueryMap,
+    files: Vec<FilePart>
+}
+
+impl FormData {
+    pub fn get(&self, name: &str)->Option<&str>{
+        self.fields.get(name)?.first().map(String::as_str)
+    }
+
+    pub fn get_all(&self, name: &str)->&[String]{
+        match self.fields.get(name) {
+            Some(values) => values,
+            None => &[]
+        }
+    }
+
+    pub fn fields(&self)->&QueryMap{
+        &self.fields
+    }
+
+    pub fn file(&self, name: &str)->Option<&FilePart>{
+        self.files.iter().find(|file| file.name == name)
+    }
+
+    pub fn files(&self)->&[FilePart]{
+        &self.files
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FormError {
+    UnsupportedContentType,
+    MissingBoundary,
+    Malformed,
+    TooManyFields,
+    FieldTooLarge,
+    FileTooLarge
+}
+
+impl Display for FormError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            FormError::UnsupportedContentType => "body is not a form",
+            FormError::MissingBoundary => "multipart body without boundary",
+            FormError::Malformed => "malformed form body",
+            FormError::TooManyFields => "too many form fields",
+            FormError::FieldTooLarge => "form field is too large",
+            FormError::FileTooLarge => "uploaded file is too large",
+        };
+        write!(f, "{message}")
+    }
+}
+
+impl Error for FormError {}
+
+impl HttpRequest {
+    pub fn form(&self)->Result<FormData, FormError>{
+        self.form_with_limits(&FormLimits::default())
+    }
+
+    pub fn form_with_limits(&self, limits: &FormLimits)->Result<FormData, FormError>{
+        let content_type: ContentType = self.typed_header().ok_or(FormError::UnsupportedContentType)?;
+        match content_type.media_type() {
+            "application/x-www-form-urlencoded" => {
+                let fields = parse_urlencoded(&self.body, limits)?;
+                Ok(FormData { fields, files: Vec::new() })
+            },
+            "multipart/form-data" => {
+                let boundary = content_type
+                    .boundary()
+                    .filter(|boundary| !boundary.is_empty())
+                    .ok_or(FormError::MissingBoundary)?;
+                parse_multipart(&self.body, boundary, limits)
+            },
+            _ => Err(FormError::UnsupportedContentType)
+        }
+    }
+}
+
+pub fn parse_urlencoded(body: &[u8], limits: &FormLimits)->Result<QueryMap, FormError>{
+    let body = String::from_utf8_lossy(body);
+    if body.split('&').filter(|pair| !pair.is_empty()).count() > limits.max_fields {
+        return Err(FormError::TooManyFields);
+    }
+    let fields = parse_query(&body);
+    if fields.values().flatten().any(|value| value.len() > limits.max_field_size) {
+        return Err(FormError::FieldTooLarge);
+    }
+
+    Ok(fields)
+}
+
+// RFC 7578, preamble and epilogue around the parts are ignored
+pub fn parse_multipart(body: &[u8], boundary: &str, limits: &FormLimits)->Result<FormData, FormError>{
+    let delimiter = format!("--{boundary}").into_bytes();
+    let next_delimiter = format!("\r\n--{boundary}").into_bytes();
+    let mut form = FormData::default();
+    let mut part_count = 0;
+
+    let mut position = if body.starts_with(&delimiter) {
+        0
+    } else {
+        find(body, &next_delimiter, 0).ok_or(FormError::Malformed)? + 2
+    };
+    loop {
+        position += delimiter.len();
+        let rest = &body[position..];
+        if rest.starts_with(b"--") {
+            break;
+        }
+        // the delimiter line may end with transport padding
+        let line_end = find(rest, b"\r\n", 0).ok_or(FormError::Malformed)?;
+        if !rest[..line_end].iter().all(|byte| matches!(byte, b' ' | b'\t')) {
+            return Err(FormError::Malformed);
+        }
+        position += line_end + 2;
+
+        let (headers, content_start) = parse_part_headers(body, position)?;
+        let content_end = find(body, &next_delimiter, content_start).ok_or(FormError::Malformed)?;
+        part_count += 1;
+        if part_count > limits.max_fields {
+            return Err(FormError::TooManyFields);
+        }
+        add_part(&mut form, headers, &body[content_start..content_end], limits)?;
+        position = content_end + 2;
+    }
+
+    Ok(form)
+}
+
+fn parse_part_headers(body: &[u8], start: usize)->Result<(HeaderMap, usize), FormError>{
+    let mut headers = HeaderMap::new();
+    let mut position = start;
+    loop {
+        let line_end = find(body, b"\r\n", position).ok_or(FormError::Malformed)?;
+        let line = &body[position..line_end];
+        position = line_end + 2;
+        if line.is_empty() {
+            return Ok((headers, position));
+        }
+        let (key, value) = parse_header_line(line).map_err(|_| FormError::Malformed)?;
+        headers.append(key, value);
+    }
+}
+
+fn add_part(form: &mut FormData, headers: HeaderMap, data: &[u8], limits: &FormLimits)->Result<(), FormError>{
+    let disposition = headers.get("Content-Disposition").ok_or(FormError::Malformed)?;
+    let (disposition_type, params) = split_parameters(disposition);
+    if !disposition_type.eq_ignore_ascii_case("form-data") {
+        return Err(FormError::Malformed);
+    }
+    let param = |name: &str| params
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.clone());
+    let name = param("name").ok_or(FormError::Malformed)?;
+
+    match param("filename") {
+        Some(filename) => {
+            if data.len() > limits.max_file_size {
+                return Err(FormError::FileTooLarge);
+            }
+            form.files.push(FilePart {
+                name,
+                filename,
+                content_type: headers.get("Content-Type").map(String::from),
+                headers,
+                data: data.to_vec()
+            });
+        },
+        None => {
+            if data.len() > limits.max_field_size {
+                return Err(FormError::FieldTooLarge);
+            }
+            form.fields
+                .entry(name)
+                .or_default()
+                .push(String::from_utf8_lossy(data).into_owned());
+        }
+    }
+
+    Ok(())
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize)->Option<usize>{
+    if from > haystack.len() {
+        return None;
+    }
+    haystack[from..]
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|index| index + from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urlencoded_form(){
+        let req = HttpRequest::test_request("POST", "/form", "Content-Type: application/x-www-form-urlencoded\r\n", b"user=ana+maria&pass=p%40ss&tag=a&tag=b");
+        let form = req.form().unwrap();
+        assert_eq!(form.get("user"), Some("ana maria"));
+        assert_eq!(form.get("pass"), Some("p@ss"));
+        assert_eq!(form.get_all("tag"), ["a", "b"]);
+        assert!(form.files().is_empty());
+    }
+    #[test]
+    fn test_multipart_form(){
+        let mut body = b"preamble\r\n--XyZ\r\nContent-Disposition: form-data; name=\"title\"\r\n\r\nHello\r\nworld\r\n--XyZ  \r\n".to_vec();
+        body.extend_from_slice(b"Content-Disposition: form-data; name=\"upload\"; filename=\"a;b.png\"\r\nContent-Type: image/png\r\n\r\n");
+        body.extend_from_slice(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0]);
+        body.extend_from_slice(b"\r\n--XyZ--\r\nepilogue");
+
+        let req = HttpRequest::test_request("POST", "/form", "Content-Type: multipart/form-data; boundary=\"XyZ\"\r\n", &body);
+        let form = req.form().unwrap();
+        assert_eq!(form.get("title"), Some("Hello\r\nworld"));
+
+        let file = form.file("upload").unwrap();
+        assert_eq!(file.filename, "a;b.png");
+        assert_eq!(file.content_type.as_deref(), Some("image/png"));
+        assert_eq!(file.data, vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0]);
+    }
+    #[test]
+    fn test_form_errors(){
+        let req = HttpRequest::test_request("POST", "/form", "Content-Type: text/plain\r\n", b"a=b");
+        assert_eq!(req.form(), Err(FormError::UnsupportedContentType));
+
+        let req = HttpRequest::test_request("POST", "/form", "Content-Type: multipart/form-data\r\n", b"--x--");
+        assert_eq!(req.form(), Err(FormError::MissingBoundary));
+
+        let req = HttpRequest::test_request("POST", "/form", "Content-Type: multipart/form-data; boundary=x\r\n", b"--x\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nno end");
+        assert_eq!(req.form(), Err(FormError::Malformed));
+
+        let req = HttpRequest::test_request("POST", "/form", "Content-Type: multipart/form-data; boundary=x\r\n", b"--x\r\n\r\nvalue\r\n--x--");
+        assert_eq!(req.form(), Err(FormError::Malformed));
+    }
+    #[test]
+    fn test_form_limits(){
+        let limits = FormLimits { max_fields: 2, max_field_size: 4, max_file_size: 8 };
+
+        let req = HttpRequest::test_request("POST", "/form", "Content-Type: application/x-www-form-urlencoded\r\n", b"a=1&b=2&c=3");
+        assert_eq!(req.form_with_limits(&limits), Err(FormError::TooManyFields));
+        let req = HttpRequest::test_request("POST", "/form", "Content-Type: application/x-www-form-urlencoded\r\n", b"a=12345");
+        assert_eq!(req.form_with_limits(&limits), Err(FormError::FieldTooLarge));
+
+        let body = b"--x\r\nContent-Disposition: form-data; name=\"f\"; filename=\"f.bin\"\r\n\r\n123456789\r\n--x--";
+        let req = HttpRequest::test_request("POST", "/form", "Content-Type: multipart/form-data; boundary=x\r\n", body);
+        assert_eq!(req.form_with_limits(&limits), Err(FormError::FileTooLarge));
+        assert!(req.form().is_ok());
+    }
+}
@@ -0,0 +1,205 @@
+use std::{error::Error, fmt::{self, Display}};
+
+use crate::http_request::is_token;
+
+// header names compare case-insensitively, entries keep their arrival order
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>
+}
+
+// a name that isn't a token, or a value that could end the field early
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidHeader {
+    pub name: String
+}
+
+impl Display for InvalidHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid header field {:?}", self.name)
+    }
+}
+
+impl Error for InvalidHeader {}
+
+impl HeaderMap {
+    pub fn new()->Self{
+        HeaderMap { entries: Vec::new() }
+    }
+
+    pub fn get(&self, name: &str)->Option<&str>{
+        self.entries
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn get_all(&self, name: &str)->Vec<&str>{
+        self.entries
+            .iter()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+            .collect()
+    }
+
+    pub fn contains_key(&self, name: &str)->bool{
+        self.get(name).is_some()
+    }
+
+    // for comma separated lists such as `Connection: keep-alive, Upgrade`
+    pub fn contains_token(&self, name: &str, token: &str)->bool{
+        self.get_all(name)
+            .iter()
+            .flat_map(|value| value.split(','))
+            .any(|item| item.trim().eq_ignore_ascii_case(token))
+    }
+
+    // append and insert don't check their input, it is checked when the headers
+    // are written. Values that come from a request or a file go through the try_ versions
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>){
+        self.entries.push((name.into(), value.into()));
+    }
+
+    pub fn try_append(&mut self, name: impl Into<String>, value: impl Into<String>)->Result<(), InvalidHeader>{
+        let (name, value) = (name.into(), value.into());
+        check_field(&name, &value)?;
+        self.append(name, value);
+        Ok(())
+    }
+
+    pub fn try_insert(&mut self, name: impl Into<String>, value: impl Into<String>)->Result<(), InvalidHeader>{
+        let (name, value) = (name.into(), value.into());
+        check_field(&name, &value)?;
+        self.insert(name, value);
+        Ok(())
+    }
+
+    // the first field that can't be written as it is
+    pub fn validate(&self)->Result<(), InvalidHeader>{
+        self.iter().try_for_each(|(name, value)| check_field(name, value))
+    }
+
+    // replaces every existing value of the header
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>){
+        let name = name.into();
+        let index = self.entries
+            .iter()
+            .position(|(key, _)| key.eq_ignore_ascii_case(&name))
+            .unwrap_or(self.entries.len());
+        self.remove(&name);
+        self.entries.insert(index, (name, value.into()));
+    }
+
+    pub fn remove(&mut self, name: &str)->Vec<String>{
+        let (removed, kept) = self.entries
+            .drain(..)
+            .partition(|(key, _)| key.eq_ignore_ascii_case(name));
+        self.entries = kept;
+
+        removed.into_iter().map(|(_, value)| value).collect()
+    }
+
+    pub fn iter(&self)->impl Iterator<Item = (&str, &str)>{
+        self.entries.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self)->usize{
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self)->bool{
+        self.entries.is_empty()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for HeaderMap {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut headers = HeaderMap::new();
+        for (key, value) in iter {
+            headers.append(key, value);
+        }
+
+        headers
+    }
+}
+
+// a CR or LF would let a value start a new field, or end the head and
+// begin a body of its own (response splitting)
+pub(crate) fn is_field_value(value: &[u8])->bool{
+    !value.iter().any(|byte| matches!(byte, b'\r' | b'\n' | 0))
+}
+
+pub(crate) fn is_valid_field(name: &str, value: &str)->bool{
+    is_token(name) && is_field_value(value.as_bytes())
+}
+
+fn check_field(name: &str, value: &str)->Result<(), InvalidHeader>{
+    match is_valid_field(name, value) {
+        true => Ok(()),
+        false => Err(InvalidHeader { name: name.to_string() })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_case_insensitive_get(){
+        let headers = HeaderMap::from_iter([("Content-Type", "text/html")]);
+        assert_eq!(headers.get("content-type"), Some("text/html"));
+        assert_eq!(headers.get("CONTENT-TYPE"), Some("text/html"));
+        assert!(headers.contains_key("Content-type"));
+        assert_eq!(headers.get("Host"), None);
+    }
+    #[test]
+    fn test_duplicates_keep_order(){
+        let mut headers = HeaderMap::new();
+        headers.append("Accept", "text/html");
+        headers.append("Host", "localhost");
+        headers.append("accept", "*/*");
+
+        assert_eq!(headers.get("Accept"), Some("text/html"));
+        assert_eq!(headers.get_all("ACCEPT"), vec!["text/html", "*/*"]);
+        assert_eq!(headers.len(), 3);
+    }
+    #[test]
+    fn test_insert_replaces_all_values(){
+        let mut headers = HeaderMap::from_iter([("A", "1"), ("B", "2"), ("a", "3")]);
+        headers.insert("a", "4");
+
+        let entries: Vec<(&str, &str)> = headers.iter().collect();
+        assert_eq!(entries, vec![("a", "4"), ("B", "2")]);
+    }
+    #[test]
+    fn test_remove(){
+        let mut headers = HeaderMap::from_iter([("A", "1"), ("B", "2"), ("a", "3")]);
+        assert_eq!(headers.remove("A"), vec!["1", "3"]);
+        assert_eq!(headers.len(), 1);
+        assert!(headers.remove("A").is_empty());
+    }
+    #[test]
+    fn test_contains_token(){
+        let headers = HeaderMap::from_iter([("Connection", "keep-alive, Upgrade")]);
+        assert!(headers.contains_token("connection", "upgrade"));
+        assert!(headers.contains_token("Connection", "keep-alive"));
+        assert!(!headers.contains_token("Connection", "close"));
+    }
+    #[test]
+    fn test_reject_invalid_fields(){
+        let mut headers = HeaderMap::new();
+        assert!(headers.try_append("Location", "/home").is_ok());
+        assert_eq!(
+            headers.try_insert("Location", "/\r\nSet-Cookie: admin=1"),
+            Err(InvalidHeader { name: "Location".to_string() })
+        );
+        assert!(headers.try_append("X-Bad Name", "1").is_err());
+        assert!(headers.try_append("", "1").is_err());
+        assert!(headers.try_append("X-Null", "a\0b").is_err());
+        assert_eq!(headers.get_all("Location"), vec!["/home"]);
+        assert!(headers.validate().is_ok());
+
+        headers.append("X-Injected", "a\nb");
+        assert_eq!(headers.validate(), Err(InvalidHeader { name: "X-Injected".to_string() }));
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    sync::{Mutex, PoisonError},
+    time::{Duration, SystemTime, UNIX_EPOCH}
+};
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+// IMF-fixdate from RFC 9110 section 5.6.7, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`
+pub fn format_http_date(time: SystemTime)->String{
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0);
+    let days = secs / 86400;
+    let secs_of_day = secs % 86400;
+    let (year, month, day) = civil_from_days(days as i64);
+    // 1970-01-01 was a Thursday
+    let weekday = WEEKDAYS[((days + 4) % 7) as usize];
+
+    format!(
+        "{weekday}, {day:02} {} {year:04} {:02}:{:02}:{:02} GMT",
+        MONTHS[(month - 1) as usize],
+        secs_of_day / 3600,
+        secs_of_day % 3600 / 60,
+        secs_of_day % 60
+    )
+}
+
+// the Date of a response, formatted at most once per second for all threads
+pub fn http_date_now()->String{
+    static CACHE: Mutex<Option<(u64, String)>> = Mutex::new(None);
+    let now = SystemTime::now();
+    let secs = unix_secs(now);
+    let mut cache = CACHE.lock().unwrap_or_else(PoisonError::into_inner);
+    match cache.as_ref() {
+        Some((cached_secs, date)) if *cached_secs == secs => date.clone(),
+        _ => {
+            let date = format_http_date(now);
+            *cache = Some((secs, date.clone()));
+            date
+        }
+    }
+}
+
+// accepts the IMF-fixdate and both obsolete formats, as RFC 9110 section 5.6.7 requires:
+// `Sun, 06 Nov 1994 08:49:37 GMT`, `Sunday, 06-Nov-94 08:49:37 GMT` and `Sun Nov  6 08:49:37 1994`
+pub fn parse_http_date(value: &str)->Option<SystemTime>{
+    let value = value.trim();
+    let (day, month, year, time) = match value.split_once(", ") {
+        Some((_, rest)) if rest.contains('-') => {
+            let (date, time) = rest.split_once(' ')?;
+            let time = time.strip_suffix(" GMT")?;
+            let mut date = date.split('-');
+            let (day, month, year) = (date.next()?, date.next()?, date.next()?);
+            // two digit years are taken as the closest year in the past (RFC 9110)
+            let year: i64 = parse_digits(year, 2)? as i64;
+            let year = if year >= 70 { 1900 + year } else { 2000 + year };
+            (day, month, year, time)
+        },
+        Some((_, rest)) => {
+            let mut parts = rest.split(' ');
+            let (day, month, year, time) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+            if parts.next() != Some("GMT") || parts.next().is_some() {
+                return None;
+            }
+            (day, month, parse_digits(year, 4)? as i64, time)
+        },
+        None => {
+            let mut parts = value.split(' ').filter(|part| !part.is_empty());
+            let (_, month, day, time, year) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+            if parts.next().is_some() {
+                return None;
+            }
+            (day, month, parse_digits(year, 4)? as i64, time)
+        }
+    };
+    let day = match day.len() {
+        1 | 2 => day.parse::<u32>().ok()?,
+        _ => return None
+    };
+    let month = MONTHS.iter().position(|name| *name == month)? as u32 + 1;
+    let mut time = time.split(':');
+    let (hour, minute, second) = (
+        parse_digits(time.next()?, 2)?,
+        parse_digits(time.next()?, 2)?,
+        parse_digits(time.next()?, 2)?
+    );
+    if time.next().is_some() || day == 0 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    if days < 0 {
+        return None;
+    }
+    let secs = days as u64 * 86400 + hour * 3600 + minute * 60 + second;
+
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+fn parse_digits(value: &str, len: usize)->Option<u64>{
+    if value.len() != len || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+// inverse of civil_from_days
+fn days_from_civil(year: i64, month: u32, day: u32)->i64{
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146097 + day_of_era - 719468
+}
+
+// HTTP dates have whole seconds, so times are compared at that precision
+pub(crate) fn unix_secs(time: SystemTime)->u64{
+    time.duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0)
+}
+
+// days since 1970-01-01 into (year, month, day), see
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64)->(i64, u32, u32){
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_format_http_date(){
+        let time = UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(format_http_date(time), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(format_http_date(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951782400);
+        assert_eq!(format_http_date(leap_day), "Tue, 29 Feb 2000 00:00:00 GMT");
+    }
+    #[test]
+    fn test_http_date_now(){
+        let before = unix_secs(SystemTime::now());
+        for _ in 0..2 {
+            let date = parse_http_date(&http_date_now()).map(unix_secs).unwrap();
+            assert!((before..=before + 1).contains(&date));
+        }
+    }
+    #[test]
+    fn test_parse_http_date(){
+        let expected = Some(UNIX_EPOCH + Duration::from_secs(784111777));
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), expected);
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), expected);
+        assert_eq!(parse_http_date("Sun Nov  6 08:49:37 1994"), expected);
+        assert_eq!(parse_http_date("Tue, 29 Feb 2000 00:00:00 GMT"), Some(UNIX_EPOCH + Duration::from_secs(951782400)));
+        assert_eq!(parse_http_date("Thursday, 01-Jan-70 00:00:00 GMT"), Some(UNIX_EPOCH));
+
+        for invalid in ["", "yesterday", "Sun, 06 Nov 1994 08:49:37 UTC", "Sun, 06 Foo 1994 08:49:37 GMT", "Sun, 06 Nov 1994 8:49:37 GMT", "Sun, 06 Nov 1994 24:00:00 GMT"] {
+            assert_eq!(parse_http_date(invalid), None, "{invalid}");
+        }
+    }
+}
@@ -1,23 +1,109 @@
-use std::{collections::HashMap, str::Lines};
+use std::{error::Error, fmt::{self, Display}};
 
+use crate::{header_map::{is_field_value, HeaderMap}, request_parser::RequestParser, uri::Uri};
+
+// request target forms from RFC 9112 section 3.2
 #[derive(Debug, PartialEq, Clone)]
 pub enum Resource {
-    Path(String)
+    // origin-form, `/where?q=now`
+    Path(Uri),
+    // absolute-form, `http://www.example.org/where?q=now`, sent to proxies
+    Absolute { scheme: String, authority: String, uri: Uri },
+    // authority-form, `www.example.com:443`, only used by CONNECT
+    Authority(String),
+    // asterisk-form, only used by a server-wide OPTIONS
+    Asterisk
+}
+
+impl Resource {
+    fn parse(method: &Method, target: &str)->Result<Resource, ParseError>{
+        if *method == Method::Connect {
+            return parse_authority_form(target).ok_or(ParseError::InvalidTarget);
+        }
+        if target.starts_with('/') {
+            return Ok(Resource::Path(target.into()));
+        }
+        if target == "*" {
+            return match method {
+                Method::Options => Ok(Resource::Asterisk),
+                _ => Err(ParseError::InvalidTarget)
+            };
+        }
+
+        parse_absolute_form(target).ok_or(ParseError::InvalidTarget)
+    }
+}
+
+impl Display for Resource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Resource::Path(uri) => write!(f, "{uri}"),
+            Resource::Absolute { scheme, authority, uri } => write!(f, "{scheme}://{authority}{uri}"),
+            Resource::Authority(authority) => write!(f, "{authority}"),
+            Resource::Asterisk => write!(f, "*")
+        }
+    }
+}
+
+// `scheme://authority[/path][?query]`, the scheme is lowercased
+fn parse_absolute_form(target: &str)->Option<Resource>{
+    let (scheme, rest) = target.split_once("://")?;
+    let mut scheme_chars = scheme.chars();
+    if !scheme_chars.next()?.is_ascii_alphabetic()
+        || !scheme_chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) {
+        return None;
+    }
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, path) = rest.split_at(authority_end);
+    if authority.is_empty() {
+        return None;
+    }
+    let uri = match path.strip_prefix('?') {
+        Some(_) => Uri::from(format!("/{path}").as_str()),
+        None => Uri::from(path)
+    };
+
+    Some(Resource::Absolute {
+        scheme: scheme.to_ascii_lowercase(),
+        authority: authority.to_string(),
+        uri
+    })
+}
+
+// `host:port` without userinfo, the port is required
+fn parse_authority_form(target: &str)->Option<Resource>{
+    let (host, port) = target.rsplit_once(':')?;
+    if host.is_empty()
+        || host.contains(['/', '?', '#', '@'])
+        || port.is_empty()
+        || !port.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(Resource::Authority(target.to_string()))
 }
 
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Method{
     Get,
+    Head,
     Post,
-    Uninitialized,
+    Put,
+    Delete,
+    Connect,
+    Options,
+    Trace,
+    Patch,
+    Extension(String),
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Version{
+    V0_9,
+    V1_0,
     V1_1,
     V2_0,
-    Uninitialized
 }
 
 #[derive(Debug, Clone)]
@@ -25,107 +111,350 @@ pub struct HttpRequest{
     pub method: Method,
     pub version: Version,
     pub resource: Resource,
-    pub headers: HashMap<String, String>,
-    pub body: String
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    // fields sent after a chunked body
+    pub trailers: HeaderMap
 }
 
-impl From<&str> for Method {
-    fn from(s: &str) -> Method{
-        match s {
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseError{
+    InvalidRequestLine,
+    InvalidMethod,
+    InvalidTarget,
+    InvalidVersion,
+    UnsupportedVersion,
+    InvalidHeader,
+    InvalidContentLength,
+    InvalidTransferEncoding,
+    UnsupportedTransferEncoding,
+    InvalidChunk,
+    UriTooLong,
+    TooManyHeaders,
+    HeadersTooLarge,
+    BodyTooLarge,
+    Truncated,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            ParseError::InvalidRequestLine => "malformed request line",
+            ParseError::InvalidMethod => "invalid method",
+            ParseError::InvalidTarget => "invalid request target",
+            ParseError::InvalidVersion => "invalid HTTP version",
+            ParseError::UnsupportedVersion => "unsupported HTTP version",
+            ParseError::InvalidHeader => "invalid header field",
+            ParseError::InvalidContentLength => "invalid Content-Length",
+            ParseError::InvalidTransferEncoding => "invalid Transfer-Encoding",
+            ParseError::UnsupportedTransferEncoding => "unsupported transfer coding",
+            ParseError::InvalidChunk => "malformed chunked body",
+            ParseError::UriTooLong => "request line is too long",
+            ParseError::TooManyHeaders => "too many header fields",
+            ParseError::HeadersTooLarge => "header fields are too large",
+            ParseError::BodyTooLarge => "body is too large",
+            ParseError::Truncated => "request is truncated",
+        };
+        write!(f, "{message}")
+    }
+}
+
+impl Error for ParseError {}
+
+impl Method {
+    pub fn as_str(&self)->&str{
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Connect => "CONNECT",
+            Method::Options => "OPTIONS",
+            Method::Trace => "TRACE",
+            Method::Patch => "PATCH",
+            Method::Extension(method) => method,
+        }
+    }
+}
+
+impl TryFrom<&str> for Method {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Method, ParseError>{
+        let method = match s {
             "GET" => Method::Get,
+            "HEAD" => Method::Head,
             "POST" => Method::Post,
-            _ => Method::Uninitialized
+            "PUT" => Method::Put,
+            "DELETE" => Method::Delete,
+            "CONNECT" => Method::Connect,
+            "OPTIONS" => Method::Options,
+            "TRACE" => Method::Trace,
+            "PATCH" => Method::Patch,
+            s if is_token(s) => Method::Extension(s.to_string()),
+            _ => return Err(ParseError::InvalidMethod)
+        };
+
+        Ok(method)
+    }
+}
+
+impl Version {
+    pub fn as_str(&self)->&'static str{
+        match self {
+            Version::V0_9 => "HTTP/0.9",
+            Version::V1_0 => "HTTP/1.0",
+            Version::V1_1 => "HTTP/1.1",
+            Version::V2_0 => "HTTP/2.0",
         }
     }
+
+    // HTTP/2 has its own framing, it can't be answered over this connection
+    pub fn is_supported(&self)->bool{
+        *self != Version::V2_0
+    }
 }
 
-impl From<&str> for Version {
-    fn from(value: &str) -> Version {
-        match value {
-            "HTTP/1.1" => Version::V1_1,
-            _ => Version::Uninitialized
+impl TryFrom<&str> for Version {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Version, ParseError> {
+        match value.as_bytes() {
+            b"HTTP/1.0" => Ok(Version::V1_0),
+            b"HTTP/1.1" => Ok(Version::V1_1),
+            b"HTTP/2.0" => Ok(Version::V2_0),
+            // later 1.x minors are understood as 1.1, see RFC 9110 section 6.2
+            [b'H', b'T', b'T', b'P', b'/', b'1', b'.', minor] if minor.is_ascii_digit() => Ok(Version::V1_1),
+            [b'H', b'T', b'T', b'P', b'/', major, b'.', minor]
+                if major.is_ascii_digit() && minor.is_ascii_digit() => {
+                Err(ParseError::UnsupportedVersion)
+            },
+            _ => Err(ParseError::InvalidVersion)
         }
     }
 }
 
-impl From<&str> for HttpRequest {
-    fn from(data: &str) -> Self {
-        let data_str = data.to_string();
-        let mut lines = data_str.lines();
-        let request_line = lines.next();
-        let (method, version, resource) = parse_request_line(request_line);
-        let headers = parse_headers(&mut lines);
-        let body = lines.collect::<String>();
+impl HttpRequest {
+    // parses one complete request, bytes after its body are ignored
+    pub fn parse(data: &[u8])->Result<HttpRequest, ParseError>{
+        let mut parser = RequestParser::new();
+        parser.feed(data);
+        parser.next_request()?.ok_or(ParseError::Truncated)
+    }
 
-        HttpRequest { 
-            method, 
-            version, 
-            resource, 
-            headers, 
-            body
+    pub fn content_length(&self)->Result<Option<usize>, ParseError>{
+        let values = self.headers.get_all("Content-Length");
+        if values.is_empty() {
+            return Ok(None);
+        }
+        // repeated but identical values (`Content-Length: 5, 5`) are allowed
+        let mut length = None;
+        for value in values.iter().flat_map(|value| value.split(',')) {
+            let value = value.trim();
+            if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(ParseError::InvalidContentLength);
+            }
+            let parsed: usize = value.parse().map_err(|_| ParseError::InvalidContentLength)?;
+            if length.is_some_and(|length| length != parsed) {
+                return Err(ParseError::InvalidContentLength);
+            }
+            length = Some(parsed);
         }
 
-        
+        Ok(length)
+    }
+
+    // HTTP/1.1 connections persist unless closed, HTTP/1.0 ones only on request
+    pub fn keep_alive(&self)->bool{
+        if self.headers.contains_token("Connection", "close") {
+            return false;
+        }
+        match self.version {
+            Version::V1_1 => true,
+            Version::V1_0 => self.headers.contains_token("Connection", "keep-alive"),
+            Version::V0_9 | Version::V2_0 => false
+        }
+    }
+
+    // HTTP/1.0 clients can't send Expect: 100-continue (RFC 9110 section 10.1.1)
+    pub fn expects_continue(&self)->bool{
+        self.version == Version::V1_1
+            && self.headers.get("Expect").is_some_and(|value| value.trim().eq_ignore_ascii_case("100-continue"))
+    }
+
+    // 100-continue is the only expectation there is, anything else gets a 417
+    pub fn has_unsupported_expectation(&self)->bool{
+        self.headers.contains_key("Expect") && !self.expects_continue() && self.version == Version::V1_1
     }
-}
 
-impl From<String> for HttpRequest {
-    fn from(value: String) -> Self {
-       value[0..].into()
+    pub fn body_text(&self)->Option<&str>{
+        std::str::from_utf8(&self.body).ok()
+    }
+
+    // authority-form and asterisk-form targets have no path
+    pub fn uri(&self)->Option<&Uri>{
+        match &self.resource {
+            Resource::Path(uri) | Resource::Absolute { uri, .. } => Some(uri),
+            Resource::Authority(_) | Resource::Asterisk => None
+        }
+    }
+
+    // the target's authority wins over the Host header (RFC 9112 section 3.2.2)
+    pub fn host(&self)->Option<&str>{
+        match &self.resource {
+            Resource::Absolute { authority, .. } | Resource::Authority(authority) => Some(authority),
+            Resource::Path(_) | Resource::Asterisk => self.headers.get("Host")
+        }
+    }
+
+    pub fn path(&self)->&str{
+        self.uri().map_or("", Uri::path)
+    }
+
+    pub fn segments(&self)->&[String]{
+        self.uri().map_or(&[], Uri::segments)
+    }
+
+    pub fn query(&self, key: &str)->Option<&str>{
+        self.uri()?.query(key)
+    }
+
+    pub fn query_all(&self, key: &str)->&[String]{
+        self.uri().map_or(&[], |uri| uri.query_all(key))
     }
 }
 
-fn parse_headers(lines: &mut Lines)->HashMap<String, String>{
-    let mut headers: HashMap<String, String> = HashMap::new();
-    while let Some(line) = lines.next() {
-        if line.is_empty(){
-            break;
+// RFC 9112 section 2.2: empty lines before the request line are ignored
+pub(crate) fn leading_empty_lines_len(data: &[u8])->usize{
+    let mut len = 0;
+    loop {
+        match &data[len..] {
+            [b'\r', b'\n', ..] => len += 2,
+            [b'\n', ..] => len += 1,
+            _ => return len
         }
-        let delimiter = ":";
-        if !line.contains(delimiter) {
+    }
+}
+
+// index right after the empty line ending the header block, bare LF is tolerated
+pub(crate) fn find_head_end(data: &[u8])->Option<usize>{
+    let mut line_start = 0;
+    for (i, byte) in data.iter().enumerate() {
+        if *byte != b'\n' {
             continue;
         }
-        let mut split = line.split(delimiter);
-        let key = split.next().unwrap_or("").trim();
-        let value = split.next().unwrap_or("").trim();
-        if !key.is_empty() {
-            headers.insert(key.to_string(), value.to_string());
+        let line = &data[line_start..i];
+        // an HTTP/0.9 request is only its request line
+        if line_start == 0 && is_simple_request(line.strip_suffix(b"\r").unwrap_or(line)) {
+            return Some(i + 1);
         }
+        if line_start > 0 && (line.is_empty() || line == b"\r") {
+            return Some(i + 1);
+        }
+        line_start = i + 1;
+    }
+
+    None
+}
+
+// `GET /path` without a version
+fn is_simple_request(line: &[u8])->bool{
+    line.starts_with(b"GET ") && line.iter().filter(|byte| **byte == b' ').count() == 1
+}
+
+pub(crate) fn parse_head(head: &[u8])->Result<HttpRequest, ParseError>{
+    let mut lines = head
+        .split(|byte| *byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line));
+    let request_line = lines.next().ok_or(ParseError::InvalidRequestLine)?;
+    let (method, resource, version) = parse_request_line(request_line)?;
+    let headers = parse_headers(lines)?;
+
+    Ok(HttpRequest {
+        method,
+        version,
+        resource,
+        headers,
+        body: Vec::new(),
+        trailers: HeaderMap::new()
+    })
+}
+
+fn parse_headers<'a>(lines: impl Iterator<Item = &'a [u8]>)->Result<HeaderMap, ParseError>{
+    let mut headers = HeaderMap::new();
+    for line in lines {
+        if line.is_empty(){
+            break;
+        }
+        let (key, value) = parse_header_line(line)?;
+        headers.append(key, value);
+    }
+
+    Ok(headers)
+}
+
+pub(crate) fn parse_header_line(line: &[u8])->Result<(&str, String), ParseError>{
+    // obsolete line folding is rejected, see RFC 9112 section 5.2
+    if line.starts_with(b" ") || line.starts_with(b"\t") {
+        return Err(ParseError::InvalidHeader);
+    }
+    // only the first colon separates the name, values like `localhost:3000` keep theirs
+    let colon = line
+        .iter()
+        .position(|byte| *byte == b':')
+        .ok_or(ParseError::InvalidHeader)?;
+    let key = std::str::from_utf8(&line[..colon]).map_err(|_| ParseError::InvalidHeader)?;
+    if !is_token(key) {
+        return Err(ParseError::InvalidHeader);
+    }
+    let value = line[colon + 1..].trim_ascii();
+    if !is_field_value(value) {
+        return Err(ParseError::InvalidHeader);
     }
 
-    headers
+    Ok((key, String::from_utf8_lossy(value).into_owned()))
 }
 
-fn parse_request_line(line: Option<&str>)->(Method, Version, Resource){
-    match line {
-        Some(str)=>{
-            let mut split_by_spaces = str.split_whitespace();
-            let method: Method = split_by_spaces
-            .next()
-            .unwrap_or("")
-            .into();
+// token = 1*tchar, see RFC 9110 section 5.6.2
+pub(crate) fn is_token(s: &str)->bool{
+    !s.is_empty() && s.bytes().all(is_tchar)
+}
 
-            let resource: Resource = Resource::Path(
-                split_by_spaces
-                .next()
-                .unwrap_or("")
-                .to_string()
-            );
-            
-            let version: Version = split_by_spaces
-            .next()
-            .unwrap_or("")
-            .into();
-            
-           
+pub(crate) fn is_tchar(b: u8)->bool{
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
 
-            (method, version, resource)
-        },
-        None => (
-            Method::Uninitialized, 
-            Version::Uninitialized, 
-            Resource::Path("".to_string())
-        )
+fn parse_request_line(line: &[u8])->Result<(Method, Resource, Version), ParseError>{
+    let line = std::str::from_utf8(line).map_err(|_| ParseError::InvalidRequestLine)?;
+    let parts: Vec<&str> = line.split(' ').collect();
+    let (method, target, version) = match parts[..] {
+        [method, target, version] => (method, target, Version::try_from(version)?),
+        ["GET", target] => ("GET", target, Version::V0_9),
+        _ => return Err(ParseError::InvalidRequestLine)
+    };
+    let method = Method::try_from(method)?;
+    if target.is_empty() || target.bytes().any(|b| b.is_ascii_control() || !b.is_ascii()) {
+        return Err(ParseError::InvalidTarget);
+    }
+
+    let resource = Resource::parse(&method, target)?;
+
+    Ok((method, resource, version))
+}
+
+// the request fixture of every module's tests, `headers` are whole lines with
+// their CRLF and a body gets its Content-Length
+#[cfg(test)]
+impl HttpRequest {
+    pub(crate) fn test_request(method: &str, target: &str, headers: &str, body: &[u8])->HttpRequest{
+        let mut data = format!("{method} {target} HTTP/1.1\r\n{headers}").into_bytes();
+        if !body.is_empty() {
+            data.extend(format!("Content-Length: {}\r\n", body.len()).into_bytes());
+        }
+        data.extend(b"\r\n");
+        data.extend_from_slice(body);
+        HttpRequest::parse(&data).unwrap()
     }
 }
 
@@ -135,28 +464,190 @@ mod tests {
 
     #[test]
     fn test_method_into(){
-        let method: Method = "GET".into();
+        let method: Method = "GET".try_into().unwrap();
         assert_eq!(method, Method::Get);
     }   
     #[test]
+    fn test_all_methods_into(){
+        let methods = [
+            ("HEAD", Method::Head),
+            ("POST", Method::Post),
+            ("PUT", Method::Put),
+            ("DELETE", Method::Delete),
+            ("CONNECT", Method::Connect),
+            ("OPTIONS", Method::Options),
+            ("TRACE", Method::Trace),
+            ("PATCH", Method::Patch),
+        ];
+        for (s, expected) in methods {
+            let method: Method = s.try_into().unwrap();
+            assert_eq!(method.as_str(), s);
+            assert_eq!(method, expected);
+        }
+    }
+    #[test]
+    fn test_extension_method_into(){
+        let method: Method = "PROPFIND".try_into().unwrap();
+        assert_eq!(method, Method::Extension("PROPFIND".to_string()));
+        assert_eq!(method.as_str(), "PROPFIND");
+
+        assert_eq!(Method::try_from("GE(T"), Err(ParseError::InvalidMethod));
+        assert_eq!(Method::try_from(""), Err(ParseError::InvalidMethod));
+    }
+    #[test]
     fn test_version_into(){
-        let version: Version = "HTTP/1.1".into();
+        let version: Version = "HTTP/1.1".try_into().unwrap();
         assert_eq!(version, Version::V1_1);
+        assert_eq!(Version::try_from("HTTP/1.0"), Ok(Version::V1_0));
+        assert_eq!(Version::try_from("HTTP/1.2"), Ok(Version::V1_1));
+        assert_eq!(Version::try_from("HTTP/2.0"), Ok(Version::V2_0));
+        assert_eq!(Version::try_from("HTTP/3.0"), Err(ParseError::UnsupportedVersion));
+        assert_eq!(Version::try_from("HTTP/1"), Err(ParseError::InvalidVersion));
+    }
+    #[test]
+    fn test_http_0_9_request() {
+        let req = HttpRequest::parse(b"GET /index.html\r\n").unwrap();
+        assert_eq!(req.version, Version::V0_9);
+        assert_eq!(req.path(), "/index.html");
+        assert!(req.headers.is_empty());
+        assert!(!req.keep_alive());
+    }
+    #[test]
+    fn test_keep_alive() {
+        let cases: [(&[u8], bool); 5] = [
+            (b"GET / HTTP/1.1\r\n\r\n", true),
+            (b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n", false),
+            (b"GET / HTTP/1.0\r\n\r\n", false),
+            (b"GET / HTTP/1.0\r\nConnection: Keep-Alive\r\n\r\n", true),
+            (b"GET / HTTP/2.0\r\n\r\n", false),
+        ];
+        for (data, expected) in cases {
+            assert_eq!(HttpRequest::parse(data).unwrap().keep_alive(), expected, "{}", String::from_utf8_lossy(data));
+        }
+    }
+    #[test]
+    fn test_expectations() {
+        let req = HttpRequest::parse(b"GET / HTTP/1.1\r\nExpect: 100-Continue\r\n\r\n").unwrap();
+        assert!(req.expects_continue());
+        assert!(!req.has_unsupported_expectation());
+
+        let req = HttpRequest::parse(b"GET / HTTP/1.1\r\nExpect: 200-ok\r\n\r\n").unwrap();
+        assert!(!req.expects_continue());
+        assert!(req.has_unsupported_expectation());
+
+        let req = HttpRequest::parse(b"GET / HTTP/1.0\r\nExpect: 100-continue\r\n\r\n").unwrap();
+        assert!(!req.expects_continue());
+        assert!(!req.has_unsupported_expectation());
     }
     #[test]
     fn test_read_http() {
-        let test_string: String = String::from("GET /greeting HTTP/1.1\r\nHost: localhost:3000\r\nUser-Agent: curl/7.64.1\r\nAccept: */*\r\n\r\nHello world"); 
-        let mut headers_expected = HashMap::new(); 
-        headers_expected.insert("Host".into(), "localhost".into());
-        headers_expected.insert("Accept".into(), "*/*".into());
-        headers_expected.insert("User-Agent".into(), "curl/7.64.1".into());
+        let test_string: String = String::from("GET /greeting HTTP/1.1\r\nHost: localhost:3000\r\nUser-Agent: curl/7.64.1\r\nAccept: */*\r\nContent-Length: 11\r\n\r\nHello world"); 
+        let headers_expected = HeaderMap::from_iter([
+            ("Host", "localhost:3000"),
+            ("User-Agent", "curl/7.64.1"),
+            ("Accept", "*/*"),
+            ("Content-Length", "11"),
+        ]);
 
-        let req: HttpRequest = test_string.into(); 
+        let req = HttpRequest::parse(test_string.as_bytes()).unwrap(); 
         assert_eq!(Method::Get, req.method); 
         assert_eq!(Version::V1_1, req.version); 
-        assert_eq!(Resource::Path("/greeting".to_string()), req.resource); 
+        assert_eq!(Resource::Path("/greeting".into()), req.resource); 
         assert_eq!(headers_expected, req.headers); 
-        assert_eq!("Hello world", req.body);
+        assert_eq!(b"Hello world", &req.body[..]);
+    }
+    #[test]
+    fn test_read_repeated_headers() {
+        let req = HttpRequest::parse(b"GET / HTTP/1.1\r\nhost: example.com\r\nAccept: text/html\r\naccept: */*\r\n\r\n").unwrap();
+        assert_eq!(req.headers.get("Host"), Some("example.com"));
+        assert_eq!(req.headers.get_all("Accept"), vec!["text/html", "*/*"]);
+    }
+    #[test]
+    fn test_request_uri_accessors() {
+        let req = HttpRequest::parse(b"GET /docs/../search?q=a%20b&q=c HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(req.path(), "/search");
+        assert_eq!(req.segments(), ["search"]);
+        assert_eq!(req.query("q"), Some("a b"));
+        assert_eq!(req.query_all("q"), ["a b", "c"]);
+    }
+    #[test]
+    fn test_request_target_forms() {
+        let req = HttpRequest::parse(b"GET HTTP://proxy.example:8080/a/../b?x=1 HTTP/1.1\r\nHost: other\r\n\r\n").unwrap();
+        assert_eq!(req.resource, Resource::Absolute {
+            scheme: "http".to_string(),
+            authority: "proxy.example:8080".to_string(),
+            uri: "/b?x=1".into()
+        });
+        assert_eq!(req.host(), Some("proxy.example:8080"));
+        assert_eq!(req.path(), "/b");
+        assert_eq!(req.query("x"), Some("1"));
+        assert_eq!(req.resource.to_string(), "http://proxy.example:8080/b?x=1");
+
+        let req = HttpRequest::parse(b"GET http://example.com?q HTTP/1.1\r\n\r\n").unwrap();
+        assert_eq!(req.path(), "/");
+
+        let req = HttpRequest::parse(b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\n").unwrap();
+        assert_eq!(req.resource, Resource::Authority("example.com:443".to_string()));
+        assert_eq!(req.uri(), None);
+        assert!(req.segments().is_empty());
+
+        let req = HttpRequest::parse(b"OPTIONS * HTTP/1.1\r\nHost: example.com\r\n\r\n").unwrap();
+        assert_eq!(req.resource, Resource::Asterisk);
+        assert_eq!(req.host(), Some("example.com"));
+        assert_eq!(req.path(), "");
+
+        let invalid: [&[u8]; 6] = [
+            b"GET * HTTP/1.1\r\n\r\n",
+            b"GET example.com HTTP/1.1\r\n\r\n",
+            b"GET http:///x HTTP/1.1\r\n\r\n",
+            b"GET 1http://a/ HTTP/1.1\r\n\r\n",
+            b"CONNECT /x HTTP/1.1\r\n\r\n",
+            b"CONNECT user@example.com:443 HTTP/1.1\r\n\r\n",
+        ];
+        for data in invalid {
+            assert_eq!(HttpRequest::parse(data).unwrap_err(), ParseError::InvalidTarget, "{}", String::from_utf8_lossy(data));
+        }
+    }
+    #[test]
+    fn test_read_binary_body() {
+        let mut data = b"POST /upload HTTP/1.1\r\nContent-Length: 6\r\n\r\n".to_vec();
+        data.extend_from_slice(&[0, 159, 146, 150, b'\r', b'\n']);
+        data.extend_from_slice(b"ignored");
+
+        let req = HttpRequest::parse(&data).unwrap();
+        assert_eq!(req.method, Method::Post);
+        assert_eq!(req.body, vec![0, 159, 146, 150, b'\r', b'\n']);
+        assert_eq!(req.body_text(), None);
+    }
+    #[test]
+    fn test_body_keeps_newlines() {
+        let req = HttpRequest::parse(b"POST / HTTP/1.1\r\nContent-Length: 12\r\n\r\nline1\nline2\n").unwrap();
+        assert_eq!(req.body_text(), Some("line1\nline2\n"));
+    }
+    #[test]
+    fn test_bare_lf_and_leading_empty_lines() {
+        let req = HttpRequest::parse(b"\r\n\nGET /a HTTP/1.1\nHost: x\n\n").unwrap();
+        assert_eq!(req.path(), "/a");
+        assert_eq!(req.headers.get("Host"), Some("x"));
+    }
+    #[test]
+    fn test_parse_errors() {
+        let cases: [(&[u8], ParseError); 11] = [
+            (b"GET / HTTP/1.1\r\nHost: x\r\n", ParseError::Truncated),
+            (b"POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort", ParseError::Truncated),
+            (b"POST /\r\n\r\n", ParseError::InvalidRequestLine),
+            (b"GET  / HTTP/1.1\r\n\r\n", ParseError::InvalidRequestLine),
+            (b"G@T / HTTP/1.1\r\n\r\n", ParseError::InvalidMethod),
+            (b"GET /\x01 HTTP/1.1\r\n\r\n", ParseError::InvalidTarget),
+            (b"GET / HTTX/1.1\r\n\r\n", ParseError::InvalidVersion),
+            (b"GET / HTTP/2.5\r\n\r\n", ParseError::UnsupportedVersion),
+            (b"GET / HTTP/1.1\r\nHost : x\r\n\r\n", ParseError::InvalidHeader),
+            (b"GET / HTTP/1.1\r\nHost: x\r\n folded\r\n\r\n", ParseError::InvalidHeader),
+            (b"POST / HTTP/1.1\r\nContent-Length: 1, 2\r\n\r\nab", ParseError::InvalidContentLength),
+        ];
+        for (data, expected) in cases {
+            assert_eq!(HttpRequest::parse(data).unwrap_err(), expected, "{}", String::from_utf8_lossy(data));
+        }
     }
 
 }
@@ -1,56 +1,157 @@
-use std::{collections::HashMap, io::{Write, Error}};
-
-#[derive(Debug, Clone, PartialEq)]
-pub struct HttpResponse<'a>{
-    version: &'a str,
-    status_code: &'a str,
-    status_text: &'a str,
-    headers: HashMap<&'a str, String>,
-    body: Option<String>
+use std::io::{Write, Error, ErrorKind};
+
+use crate::{body::Body, cookie::Cookie, header_map::{HeaderMap, InvalidHeader}, http_request::Version, status_code::StatusCode};
+
+#[derive(Debug)]
+pub struct HttpResponse{
+    version: Version,
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Option<Body>
 }
 
-impl <'a>Default for HttpResponse<'a> {
+impl Default for HttpResponse {
     fn default() -> Self {
         Self { 
-            version: "HTTP/1.1", 
-            status_code: "200", 
-            status_text: "OK", 
-            headers: HashMap::new(), 
+            version: Version::V1_1, 
+            status: StatusCode::OK, 
+            headers: HeaderMap::new(), 
             body: None 
         }
     }
 }
 
-impl<'a> HttpResponse<'a>{
+impl HttpResponse{
     pub fn new(
-        status_code: &'a str,
-        headers: Option<HashMap<&'a str, String>>,
+        status: StatusCode,
+        headers: Option<HeaderMap>,
         body: Option<String>
     )->Self{
-        let mut http_response = Self::default();
-        http_response.status_code = status_code;
+        let mut http_response = Self{
+            status,
+            ..Self::default()
+        };
         match headers {
-            Some(headers) => {http_response.headers = headers},
+            Some(headers) => {
+                http_response.headers = headers;
+                // custom headers only replace the default Content-Type if they have their own
+                if body.is_some() && !http_response.headers.contains_key("Content-Type") {
+                    http_response.headers.insert("Content-Type", "text/html".to_string());
+                }
+            },
             None=>{
                 http_response.headers.insert("Content-Type", "text/html".to_string());
-                http_response.headers.insert("Connection", "keep-alive".to_string());
             }
         }
-        http_response.status_text = match status_code {
-            "101" => "Switching Protocols",
-            "200" => "OK",
-            "400" => "Bad request",
-            "404" => "Not Found",
-            "500" => "Server error",
-            _ => "Unknown"
-        };
-        http_response.body = body;
+        http_response.body = body.map(Body::Text);
         http_response
     }
 
+    pub fn builder()->ResponseBuilder{
+        ResponseBuilder::default()
+    }
+
+    pub fn ok()->Self{
+        Self::builder().build()
+    }
+
+    pub fn not_found()->Self{
+        Self::builder().status(StatusCode::NOT_FOUND).build()
+    }
+
+    pub fn html(body: impl Into<String>)->Self{
+        Self::builder()
+            .header("Content-Type", "text/html; charset=utf-8")
+            .body(Body::Text(body.into()))
+    }
+
+    pub fn text(body: impl Into<String>)->Self{
+        Self::builder()
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(Body::Text(body.into()))
+    }
+
+    // the default redirect is a 302, the constructors below pick another status
+    pub fn redirect(location: impl Into<String>)->Self{
+        Self::found(location)
+    }
+
+    // 301 and 302 let clients turn a POST into a GET, 307 and 308 keep the method
+    pub fn moved_permanently(location: impl Into<String>)->Self{
+        Self::redirect_with_status(StatusCode::MOVED_PERMANENTLY, location)
+    }
+
+    pub fn found(location: impl Into<String>)->Self{
+        Self::redirect_with_status(StatusCode::FOUND, location)
+    }
+
+    // answers a POST with a GET of another resource
+    pub fn see_other(location: impl Into<String>)->Self{
+        Self::redirect_with_status(StatusCode::SEE_OTHER, location)
+    }
+
+    pub fn temporary_redirect(location: impl Into<String>)->Self{
+        Self::redirect_with_status(StatusCode::TEMPORARY_REDIRECT, location)
+    }
+
+    pub fn permanent_redirect(location: impl Into<String>)->Self{
+        Self::redirect_with_status(StatusCode::PERMANENT_REDIRECT, location)
+    }
+
+    pub fn redirect_with_status(status: StatusCode, location: impl Into<String>)->Self{
+        Self::builder()
+            .status(status)
+            .header("Location", location)
+            .build()
+    }
+
+    pub fn status(&self)->StatusCode{
+        self.status
+    }
+
+    pub fn set_status(&mut self, status: StatusCode){
+        self.status = status;
+    }
+
+    pub fn body(&self)->Option<&Body>{
+        self.body.as_ref()
+    }
+
+    pub fn set_body(&mut self, body: impl Into<Body>){
+        self.body = Some(body.into());
+    }
+
+    pub fn take_body(&mut self)->Option<Body>{
+        self.body.take()
+    }
+
+    pub fn version(&self)->&Version{
+        &self.version
+    }
+
+    // answer with the version the client speaks, HTTP/0.9 gets a bare body
+    pub fn set_version(&mut self, version: &Version){
+        self.version = match version {
+            Version::V2_0 => Version::V1_1,
+            version => version.clone()
+        };
+    }
+
+    pub fn headers(&self)->&HeaderMap{
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self)->&mut HeaderMap{
+        &mut self.headers
+    }
+
+    // every cookie goes out as its own Set-Cookie header
+    pub fn add_cookie(&mut self, cookie: &Cookie){
+        self.headers.append("Set-Cookie", cookie.to_string());
+    }
+
     pub fn get_headers_as_string(&self)->String{
         self.headers
-            .clone()
             .iter()
             .fold(String::from(""), 
             |acc, (key, value)|{
@@ -58,9 +159,36 @@ impl<'a> HttpResponse<'a>{
         })
     }
 
+    // true once the response says the connection ends after it
+    pub fn closes_connection(&self)->bool{
+        self.headers.contains_token("Connection", "close")
+    }
+
+    fn is_chunked(&self)->bool{
+        self.status.allows_body()
+            && self.version == Version::V1_1
+            && self.body.as_ref().is_some_and(|body| body.len().is_none())
+    }
+
     fn set_content_length_header(&mut self){
+        // these never have a body, and a 304's length would be the one of the 200
+        if !self.status.allows_body() {
+            return;
+        }
         let body_length: String =  match &self.body{
-            Some(body)=>body.len().to_string(),
+            Some(body)=>match body.len() {
+                Some(len)=>len.to_string(),
+                // HTTP/1.0 has no chunked coding, the body ends with the connection
+                None if self.version != Version::V1_1=>{
+                    self.headers.insert("Connection", "close");
+                    return;
+                },
+                None=>{
+                    self.headers.remove("Content-Length");
+                    self.headers.insert("Transfer-Encoding", "chunked");
+                    return;
+                }
+            },
             None=>"0".to_string()
         };
      
@@ -68,23 +196,120 @@ impl<'a> HttpResponse<'a>{
 
     }
 
+    // nothing is written when a field could split the response, the caller
+    // still has the stream to answer with an error instead
+    fn validate_headers(&self)->Result<(), Error>{
+        self.headers.validate().map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
     pub fn send_response(&mut self, stream: &mut impl Write)-> Result<(), Error>{
+        self.validate_headers()?;
+        self.set_content_length_header();
+        stream.write_all(self.get_head_as_string().as_bytes())?;
+        // the body is written once, files and readers straight from their source
+        let chunked = self.is_chunked();
+        match self.body.take() {
+            Some(_) if !self.status.allows_body() => {},
+            Some(body) if chunked => body.write_chunked(stream)?,
+            Some(body) => body.write_to(stream)?,
+            None => {}
+        }
+        stream.flush()
+    }
+
+    // answer to HEAD: same headers and Content-Length as GET, but no body
+    pub fn send_head(&mut self, stream: &mut impl Write)-> Result<(), Error>{
+        self.validate_headers()?;
         self.set_content_length_header();
-        stream.write_all(String::from(self.clone()).as_bytes())?;
+        stream.write_all(self.get_head_as_string().as_bytes())?;
         stream.flush()
     }
+
+    // the response as send_response writes it, with its body read and framed
+    pub fn try_into_bytes(mut self)->Result<Vec<u8>, Error>{
+        let mut bytes = Vec::new();
+        self.send_response(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn get_head_as_string(&self)->String{
+        let HttpResponse {version, status, ..} = self;
+        if *version == Version::V0_9 {
+            return String::new();
+        }
+        let version = version.as_str();
+        // a code without a registered reason is sent with an empty one
+        let reason = status.canonical_reason().unwrap_or("");
+        let headers_string = self.get_headers_as_string();
+        format!("{version} {status} {reason}\r\n{headers_string}\r\n")
+    }
 }
 
 
-impl<'a> From<HttpResponse<'a>> for String {
+#[derive(Debug, Default)]
+pub struct ResponseBuilder {
+    response: HttpResponse
+}
+
+impl ResponseBuilder {
+    pub fn status(mut self, status: StatusCode)->Self{
+        self.response.status = status;
+        self
+    }
+
+    pub fn version(mut self, version: Version)->Self{
+        self.response.set_version(&version);
+        self
+    }
+
+    // appends, so a header can be given several times
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>)->Self{
+        self.response.headers.append(name, value);
+        self
+    }
+
+    pub fn try_header(mut self, name: impl Into<String>, value: impl Into<String>)->Result<Self, InvalidHeader>{
+        self.response.headers.try_append(name, value)?;
+        Ok(self)
+    }
+
+    pub fn headers(mut self, headers: HeaderMap)->Self{
+        for (name, value) in headers.iter() {
+            self.response.headers.append(name, value);
+        }
+        self
+    }
+
+    pub fn cookie(mut self, cookie: &Cookie)->Self{
+        self.response.add_cookie(cookie);
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Body>)->HttpResponse{
+        self.response.body = Some(body.into());
+        self.response
+    }
+
+    pub fn build(self)->HttpResponse{
+        self.response
+    }
+}
+
+// for buffered bodies only, a file, reader or stream is left out since
+// reading it could fail, try_into_bytes writes those too
+impl From<HttpResponse> for Vec<u8> {
     fn from(value: HttpResponse) -> Self {
-        let HttpResponse {version, status_code, body, status_text ,..} = &value;
-        let headers_string = value.get_headers_as_string();
-        let body_string = match body {
-            Some(body)=>body,
-            None=>""
-        };
-        format!("{version} {status_code} {status_text}\r\n{headers_string}\r\n{body_string}")
+        let mut bytes = value.get_head_as_string().into_bytes();
+        if let Some(body) = value.body.as_ref().and_then(Body::as_bytes) {
+            bytes.extend_from_slice(body);
+        }
+        bytes
+    }
+}
+
+impl From<HttpResponse> for String {
+    fn from(value: HttpResponse) -> Self {
+        String::from_utf8_lossy(&Vec::from(value)).into_owned()
     }
 }
 
@@ -95,24 +320,191 @@ mod test {
 
     #[test]
     fn test_get_headers_from_string(){
-        let mut headers = HashMap::new();
+        let mut headers = HeaderMap::new();
         headers.insert("Content-Type", "text/html".to_string());
         headers.insert("Authentication", "Bearer 123456".to_string());
 
-        let res = HttpResponse::new("200", Some(headers), None);       
+        let res = HttpResponse::new(StatusCode::OK, Some(headers), None);       
         assert_eq!(res.get_headers_as_string(), "Content-Type: text/html\r\nAuthentication: Bearer 123456\r\n".to_string());
     }
     #[test]
     fn test_string_from_http_response(){
-        let mut headers = HashMap::new();
+        let mut headers = HeaderMap::new();
         headers.insert("Content-Type", "text/html".to_string());
         headers.insert("Authentication", "Bearer 123456".to_string());
-        let response = HttpResponse::new("404", Some(headers), Some(String::from("Hello world")));
+        let response = HttpResponse::new(StatusCode::NOT_FOUND, Some(headers), Some(String::from("Hello world")));
         
         let response_string = String::from(response);
         let expected_string = "HTTP/1.1 404 Not Found\r\nContent-Type: text/html\r\nAuthentication: Bearer 123456\r\n\r\nHello world".to_string();
         assert_eq!(expected_string, response_string);
     }
+    #[test]
+    fn test_several_cookies(){
+        let mut response = HttpResponse::new(StatusCode::OK, Some(HeaderMap::new()), None);
+        response.add_cookie(&Cookie::new("session", "abc").unwrap().http_only(true));
+        response.add_cookie(&Cookie::new("theme", "dark").unwrap().path("/").unwrap());
+
+        assert_eq!(
+            response.get_headers_as_string(),
+            "Set-Cookie: session=abc; HttpOnly\r\nSet-Cookie: theme=dark; Path=/\r\n"
+        );
+    }
+    #[test]
+    fn test_response_version(){
+        let mut response = HttpResponse::new(StatusCode::OK, Some(HeaderMap::new()), Some(String::from("Hello")));
+        response.set_version(&Version::V1_0);
+        let mut output: Vec<u8> = Vec::new();
+        response.send_response(&mut output).unwrap();
+        assert_eq!(output, b"HTTP/1.0 200 OK\r\nContent-Type: text/html\r\nContent-Length: 5\r\n\r\nHello");
+
+        response.set_body("Hello");
+        response.set_version(&Version::V0_9);
+        assert_eq!(String::from(response), "Hello");
+    }
+    #[test]
+    fn test_status_line(){
+        let response = HttpResponse::new(StatusCode::CREATED, Some(HeaderMap::new()), None);
+        assert_eq!(String::from(response), "HTTP/1.1 201 Created\r\n\r\n");
+
+        let response = HttpResponse::new(StatusCode::from_u16(299).unwrap(), Some(HeaderMap::new()), None);
+        assert_eq!(String::from(response), "HTTP/1.1 299 \r\n\r\n");
+    }
+    #[test]
+    fn test_custom_headers_keep_default_content_type(){
+        let mut headers = HeaderMap::new();
+        headers.insert("Cache-Control", "no-cache");
+        let response = HttpResponse::new(StatusCode::OK, Some(headers), Some(String::from("<p>hi</p>")));
+        assert_eq!(response.get_headers_as_string(), "Cache-Control: no-cache\r\nContent-Type: text/html\r\n");
+
+        let response = HttpResponse::new(StatusCode::NO_CONTENT, Some(HeaderMap::new()), None);
+        assert!(response.headers().is_empty());
+    }
+    #[test]
+    fn test_response_builder(){
+        let name = String::from("X-Request-Id");
+        let response = HttpResponse::builder()
+            .status(StatusCode::CREATED)
+            .header(name.clone(), format!("{}", 42))
+            .header("Link", "</a>; rel=preload")
+            .header("Link", "</b>; rel=preload")
+            .cookie(&Cookie::new("id", "1").unwrap())
+            .body("done");
+        drop(name);
+
+        assert_eq!(response.status(), StatusCode::CREATED);
+        assert_eq!(response.headers().get("x-request-id"), Some("42"));
+        assert_eq!(response.headers().get_all("Link").len(), 2);
+        assert_eq!(response.headers().get("Set-Cookie"), Some("id=1"));
+        assert_eq!(response.body().and_then(Body::as_bytes), Some(&b"done"[..]));
+    }
+    #[test]
+    fn test_response_constructors(){
+        assert_eq!(String::from(HttpResponse::ok()), "HTTP/1.1 200 OK\r\n\r\n");
+        assert_eq!(HttpResponse::not_found().status(), StatusCode::NOT_FOUND);
+
+        let html = HttpResponse::html("<h1>Hi</h1>");
+        assert_eq!(html.headers().get("Content-Type"), Some("text/html; charset=utf-8"));
+        assert_eq!(html.body().and_then(Body::as_bytes), Some(&b"<h1>Hi</h1>"[..]));
+        assert_eq!(HttpResponse::text("hi").headers().get("Content-Type"), Some("text/plain; charset=utf-8"));
+
+        let redirect = HttpResponse::redirect("/login");
+        assert_eq!(redirect.status(), StatusCode::FOUND);
+        assert_eq!(redirect.headers().get("Location"), Some("/login"));
+
+        let redirects = [
+            (HttpResponse::moved_permanently("/a"), StatusCode::MOVED_PERMANENTLY),
+            (HttpResponse::found("/a"), StatusCode::FOUND),
+            (HttpResponse::see_other("/a"), StatusCode::SEE_OTHER),
+            (HttpResponse::temporary_redirect("/a"), StatusCode::TEMPORARY_REDIRECT),
+            (HttpResponse::permanent_redirect("/a"), StatusCode::PERMANENT_REDIRECT),
+        ];
+        for (redirect, status) in redirects {
+            assert_eq!(redirect.status(), status);
+            assert_eq!(redirect.headers().get("Location"), Some("/a"));
+            assert_eq!(String::from(redirect), format!("HTTP/1.1 {status} {}\r\nLocation: /a\r\n\r\n", status.canonical_reason().unwrap()));
+        }
+    }
+    #[test]
+    fn test_binary_body(){
+        let mut response = HttpResponse::new(StatusCode::OK, Some(HeaderMap::new()), None);
+        response.set_body(vec![0, 159, 146, 150]);
+        let mut output: Vec<u8> = Vec::new();
+        response.send_response(&mut output).unwrap();
+
+        assert!(output.starts_with(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n"));
+        assert!(output.ends_with(&[0, 159, 146, 150]));
+    }
+    #[test]
+    fn test_reader_body(){
+        let mut response = HttpResponse::builder()
+            .header("Content-Type", "application/octet-stream")
+            .body(Body::from_reader(std::io::Cursor::new(vec![7; 100]), 64));
+        let mut output: Vec<u8> = Vec::new();
+        response.send_response(&mut output).unwrap();
+
+        let head = b"HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: 64\r\n\r\n";
+        assert_eq!(&output[..head.len()], head);
+        assert_eq!(output[head.len()..], [7; 64]);
+
+        let reader = || Body::from_reader(std::io::Cursor::new(vec![7; 4]), 4);
+        assert_eq!(String::from(HttpResponse::builder().body(reader())), "HTTP/1.1 200 OK\r\n\r\n");
+        let bytes = HttpResponse::builder().body(reader()).try_into_bytes().unwrap();
+        assert_eq!(bytes, b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n\x07\x07\x07\x07");
+        let short = HttpResponse::builder().body(Body::from_reader(std::io::Cursor::new(vec![7; 2]), 4));
+        assert!(short.try_into_bytes().is_err());
+    }
+    #[test]
+    fn test_stream_body(){
+        let chunks = || vec![Ok(b"line 1\n".to_vec()), Ok(b"line 2\n".to_vec())];
+        let mut response = HttpResponse::builder()
+            .header("Content-Type", "text/plain")
+            .body(Body::stream(chunks()));
+        let mut output: Vec<u8> = Vec::new();
+        response.send_response(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\n\r\n\
+            7\r\nline 1\n\r\n7\r\nline 2\n\r\n0\r\n\r\n"
+        );
+        assert!(!response.closes_connection());
+
+        let mut response = HttpResponse::builder().version(Version::V1_0).body(Body::stream(chunks()));
+        let mut output: Vec<u8> = Vec::new();
+        response.send_response(&mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "HTTP/1.0 200 OK\r\nConnection: close\r\n\r\nline 1\nline 2\n");
+        assert!(response.closes_connection());
+
+        let mut response = HttpResponse::builder().status(StatusCode::NO_CONTENT).body(Body::stream(chunks()));
+        let mut output: Vec<u8> = Vec::new();
+        response.send_response(&mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "HTTP/1.1 204 No Content\r\n\r\n");
+    }
+    #[test]
+    fn test_no_response_splitting(){
+        let location = "/next\r\nContent-Length: 0\r\n\r\nHTTP/1.1 200 OK";
+        assert!(HttpResponse::builder().try_header("Location", location).is_err());
+
+        let mut output: Vec<u8> = Vec::new();
+        let error = HttpResponse::found(location).send_response(&mut output).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(output.is_empty());
+
+        let mut response = HttpResponse::ok();
+        response.headers_mut().append("Bad Name", "1");
+        assert!(response.send_head(&mut output).is_err());
+        assert!(output.is_empty());
+    }
+    #[test]
+    fn test_send_head_has_no_body(){
+        let mut response = HttpResponse::new(StatusCode::OK, None, Some(String::from("Hello world")));
+        let mut output: Vec<u8> = Vec::new();
+        response.send_head(&mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(output.contains("Content-Length: 11\r\n"));
+        assert!(output.ends_with("\r\n\r\n"));
+    }
 
 
 }
\ No newline at end of file
@@ -0,0 +1,109 @@
+use std::{error::Error, fmt::{self, Display}};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{header_map::HeaderMap, http_request::HttpRequest, http_response::HttpResponse, status_code::StatusCode, typed_headers::ContentType};
+
+#[derive(Debug)]
+pub enum JsonError {
+    ContentType,
+    Syntax(serde_json::Error)
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::ContentType => write!(f, "Content-Type is not application/json"),
+            JsonError::Syntax(e) => write!(f, "invalid JSON body: {e}")
+        }
+    }
+}
+
+impl Error for JsonError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            JsonError::ContentType => None,
+            JsonError::Syntax(e) => Some(e)
+        }
+    }
+}
+
+impl HttpRequest {
+    pub fn json<T: DeserializeOwned>(&self)->Result<T, JsonError>{
+        let content_type: ContentType = self.typed_header().ok_or(JsonError::ContentType)?;
+        if !is_json_media_type(content_type.media_type()) {
+            return Err(JsonError::ContentType);
+        }
+
+        serde_json::from_slice(&self.body).map_err(JsonError::Syntax)
+    }
+}
+
+impl HttpResponse {
+    pub fn json<T: Serialize>(value: &T)->Self{
+        Self::json_with_status(StatusCode::OK, value)
+    }
+
+    // a value that can't be serialized becomes an empty 500 response
+    pub fn json_with_status<T: Serialize>(status: StatusCode, value: &T)->Self{
+        match serde_json::to_string(value) {
+            Ok(body) => {
+                let mut headers = HeaderMap::new();
+                headers.insert("Content-Type", "application/json".to_string());
+                HttpResponse::new(status, Some(headers), Some(body))
+            },
+            Err(_) => HttpResponse::new(StatusCode::INTERNAL_SERVER_ERROR, Some(HeaderMap::new()), None)
+        }
+    }
+}
+
+// application/json and structured syntax suffixes such as application/problem+json
+fn is_json_media_type(media_type: &str)->bool{
+    media_type == "application/json" || (media_type.starts_with("application/") && media_type.ends_with("+json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Login {
+        user: String,
+        remember: bool
+    }
+
+    #[test]
+    fn test_request_json(){
+        let req = HttpRequest::test_request("POST", "/login", "Content-Type: application/json; charset=utf-8\r\n", br#"{"user": "ana", "remember": true}"#);
+        let login: Login = req.json().unwrap();
+        assert_eq!(login, Login { user: "ana".to_string(), remember: true });
+
+        let req = HttpRequest::test_request("POST", "/login", "Content-Type: application/vnd.api+json\r\n", br#"{"user": "ana", "remember": false}"#);
+        assert!(req.json::<Login>().is_ok());
+    }
+    #[test]
+    fn test_request_json_errors(){
+        let req = HttpRequest::test_request("POST", "/login", "Content-Type: text/plain\r\n", br#"{"user": "ana", "remember": true}"#);
+        assert!(matches!(req.json::<Login>(), Err(JsonError::ContentType)));
+
+        let req = HttpRequest::test_request("POST", "/login", "Content-Type: application/json\r\n", br#"{"user": "ana"}"#);
+        assert!(matches!(req.json::<Login>(), Err(JsonError::Syntax(_))));
+    }
+    #[test]
+    fn test_response_json(){
+        let login = Login { user: "ana".to_string(), remember: true };
+        let response = String::from(HttpResponse::json(&login));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Type: application/json\r\n"));
+        assert!(response.ends_with(r#"{"user":"ana","remember":true}"#));
+
+        // JSON object keys must be strings
+        let invalid: BTreeMap<(u8, u8), u8> = BTreeMap::from([((1, 2), 3)]);
+        let response = String::from(HttpResponse::json(&invalid));
+        assert!(response.starts_with("HTTP/1.1 500 "));
+    }
+}
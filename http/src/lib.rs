@@ -1,4 +1,19 @@
+pub mod body;
+pub mod chunked;
+pub mod compression;
+pub mod conditional;
+pub mod cookie;
+pub mod form;
+pub mod header_map;
+pub mod http_date;
 pub mod http_request;
 pub mod http_response;
+#[cfg(feature = "json")]
+pub mod json;
+pub mod range;
+pub mod request_parser;
+pub mod status_code;
+pub mod typed_headers;
+pub mod uri;
 
 
@@ -0,0 +1,189 @@
+use std::{
+    io::{self, Read, Seek, SeekFrom},
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH}
+};
+
+use crate::{
+    body::Body,
+    header_map::HeaderMap,
+    http_date::unix_secs,
+    http_request::{HttpRequest, Method},
+    http_response::HttpResponse,
+    status_code::StatusCode,
+    typed_headers::{EntityTag, IfRange, Range}
+};
+
+// a client asking for many small ranges costs more than the whole file
+const MAX_RANGES: usize = 64;
+
+// how a GET with a Range header should be answered, RFC 9110 section 14
+#[derive(Debug, Clone, PartialEq)]
+pub enum RangeSelection {
+    Full,
+    // inclusive (first, last) offsets, sorted and without overlaps
+    Partial(Vec<(u64, u64)>),
+    Unsatisfiable
+}
+
+impl HttpRequest {
+    // `etag` and `last_modified` validate If-Range, without them it never matches
+    pub fn range_selection(&self, len: u64, etag: Option<&EntityTag>, last_modified: Option<SystemTime>)->RangeSelection{
+        if self.method != Method::Get {
+            return RangeSelection::Full;
+        }
+        let Some(range) = self.typed_header::<Range>() else {
+            return RangeSelection::Full;
+        };
+        if self.headers.contains_key("If-Range") {
+            let unchanged = match self.typed_header::<IfRange>() {
+                Some(IfRange::Tag(tag)) => etag.is_some_and(|etag| etag.strong_eq(&tag)),
+                Some(IfRange::Date(date)) => last_modified.is_some_and(|modified| unix_secs(modified) == unix_secs(date)),
+                None => false
+            };
+            if !unchanged {
+                return RangeSelection::Full;
+            }
+        }
+        if range.ranges().len() > MAX_RANGES {
+            return RangeSelection::Full;
+        }
+
+        let mut ranges: Vec<(u64, u64)> = range.ranges().iter().filter_map(|range| range.resolve(len)).collect();
+        if ranges.is_empty() {
+            return RangeSelection::Unsatisfiable;
+        }
+        // overlapping and adjacent ranges are coalesced (RFC 9110 section 14.1.2)
+        ranges.sort_unstable();
+        let mut coalesced: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+        for (first, last) in ranges {
+            match coalesced.last_mut() {
+                Some((_, previous_last)) if first <= *previous_last + 1 => {
+                    *previous_last = (*previous_last).max(last);
+                },
+                _ => coalesced.push((first, last))
+            }
+        }
+
+        RangeSelection::Partial(coalesced)
+    }
+}
+
+impl HttpResponse {
+    // `headers` describe the whole representation of `len` bytes, one range is
+    // streamed from `body` and several are sent as multipart/byteranges (RFC 9110 section 14.6)
+    pub fn partial_content(
+        mut headers: HeaderMap,
+        mut body: impl Read + Seek + Send + 'static,
+        len: u64,
+        ranges: &[(u64, u64)]
+    )->io::Result<Self>{
+        let content_range = |(first, last): (u64, u64)| format!("bytes {first}-{last}/{len}");
+
+        let partial_body = match ranges {
+            [(first, last)] => {
+                headers.insert("Content-Range", content_range((*first, *last)));
+                body.seek(SeekFrom::Start(*first))?;
+                Body::from_reader(body, last - first + 1)
+            },
+            ranges => {
+                let boundary = multipart_boundary();
+                let content_type = headers.remove("Content-Type").pop();
+                let mut multipart = Vec::new();
+                for range in ranges {
+                    multipart.extend_from_slice(format!("--{boundary}\r\n").as_bytes());
+                    if let Some(content_type) = &content_type {
+                        multipart.extend_from_slice(format!("Content-Type: {content_type}\r\n").as_bytes());
+                    }
+                    multipart.extend_from_slice(format!("Content-Range: {}\r\n\r\n", content_range(*range)).as_bytes());
+                    let (first, last) = *range;
+                    body.seek(SeekFrom::Start(first))?;
+                    (&mut body).take(last - first + 1).read_to_end(&mut multipart)?;
+                    multipart.extend_from_slice(b"\r\n");
+                }
+                multipart.extend_from_slice(format!("--{boundary}--\r\n").as_bytes());
+                headers.insert("Content-Type", format!("multipart/byteranges; boundary={boundary}"));
+                Body::Bytes(multipart)
+            }
+        };
+        Ok(HttpResponse::builder()
+            .status(StatusCode::PARTIAL_CONTENT)
+            .headers(headers)
+            .body(partial_body))
+    }
+
+    pub fn range_not_satisfiable(mut headers: HeaderMap, len: u64)->Self{
+        headers.remove("Content-Type");
+        headers.insert("Content-Range", format!("bytes */{len}"));
+        HttpResponse::new(StatusCode::RANGE_NOT_SATISFIABLE, Some(headers), None)
+    }
+}
+
+// unique per response, so it is very unlikely to appear inside the parts
+fn multipart_boundary()->String{
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_nanos() as u64).unwrap_or(0);
+    format!("byteranges_{nanos:016x}{:04x}", COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn test_range_selection(){
+        assert_eq!(HttpRequest::test_request("GET", "/video", "", b"").range_selection(100, None, None), RangeSelection::Full);
+        assert_eq!(HttpRequest::test_request("GET", "/video", "Range: bytes=0-9\r\n", b"").range_selection(100, None, None), RangeSelection::Partial(vec![(0, 9)]));
+        assert_eq!(
+            HttpRequest::test_request("GET", "/video", "Range: bytes=-10, 0-4, 3-9\r\n", b"").range_selection(100, None, None),
+            RangeSelection::Partial(vec![(0, 9), (90, 99)])
+        );
+        assert_eq!(HttpRequest::test_request("GET", "/video", "Range: bytes=100-\r\n", b"").range_selection(100, None, None), RangeSelection::Unsatisfiable);
+        assert_eq!(HttpRequest::test_request("GET", "/video", "Range: lines=1-2\r\n", b"").range_selection(100, None, None), RangeSelection::Full);
+
+        let head = HttpRequest::parse(b"HEAD /video HTTP/1.1\r\nRange: bytes=0-9\r\n\r\n").unwrap();
+        assert_eq!(head.range_selection(100, None, None), RangeSelection::Full);
+    }
+    #[test]
+    fn test_if_range(){
+        let etag = EntityTag::strong("v1");
+        let modified = UNIX_EPOCH + Duration::from_secs(784111777);
+        let partial = RangeSelection::Partial(vec![(0, 0)]);
+
+        let req = HttpRequest::test_request("GET", "/video", "Range: bytes=0-0\r\nIf-Range: \"v1\"\r\n", b"");
+        assert_eq!(req.range_selection(10, Some(&etag), None), partial);
+        assert_eq!(req.range_selection(10, Some(&EntityTag::strong("v2")), None), RangeSelection::Full);
+        assert_eq!(req.range_selection(10, None, Some(modified)), RangeSelection::Full);
+
+        let req = HttpRequest::test_request("GET", "/video", "Range: bytes=0-0\r\nIf-Range: Sun, 06 Nov 1994 08:49:37 GMT\r\n", b"");
+        assert_eq!(req.range_selection(10, None, Some(modified + Duration::from_millis(500))), partial);
+        assert_eq!(req.range_selection(10, None, Some(modified + Duration::from_secs(1))), RangeSelection::Full);
+    }
+    #[test]
+    fn test_partial_content(){
+        let headers: HeaderMap = [("Content-Type", "text/plain"), ("Accept-Ranges", "bytes")].into_iter().collect();
+        let response = String::from_utf8(HttpResponse::partial_content(headers.clone(), Cursor::new(b"0123456789"), 10, &[(2, 4)]).unwrap().try_into_bytes().unwrap()).unwrap();
+        assert!(response.starts_with("HTTP/1.1 206 Partial Content\r\n"));
+        assert!(response.contains("Content-Range: bytes 2-4/10\r\n"));
+        assert!(response.ends_with("\r\n\r\n234"));
+
+        let response = String::from_utf8(HttpResponse::partial_content(headers, Cursor::new(b"0123456789"), 10, &[(0, 1), (8, 9)]).unwrap().try_into_bytes().unwrap()).unwrap();
+        let boundary = response
+            .split("boundary=")
+            .nth(1)
+            .and_then(|rest| rest.split("\r\n").next())
+            .unwrap();
+        assert!(response.contains(&format!(
+            "--{boundary}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/10\r\n\r\n01\r\n\
+            --{boundary}\r\nContent-Type: text/plain\r\nContent-Range: bytes 8-9/10\r\n\r\n89\r\n--{boundary}--\r\n"
+        )));
+    }
+    #[test]
+    fn test_range_not_satisfiable(){
+        let headers: HeaderMap = [("Content-Type", "text/plain")].into_iter().collect();
+        let response = String::from(HttpResponse::range_not_satisfiable(headers, 10));
+        assert_eq!(response, "HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */10\r\n\r\n");
+    }
+}
@@ -0,0 +1,307 @@
+use crate::{
+    chunked::ChunkedDecoder,
+    http_request::{HttpRequest, ParseError, find_head_end, leading_empty_lines_len, parse_head}
+};
+
+#[derive(Debug)]
+enum BodyFraming {
+    Length(usize),
+    Chunked(ChunkedDecoder)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    pub max_request_line: usize,
+    pub max_headers: usize,
+    // bytes of all header fields together
+    pub max_header_size: usize,
+    pub max_body_size: usize
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_request_line: 8 * 1024,
+            max_headers: 100,
+            max_header_size: 16 * 1024,
+            max_body_size: 10 * 1024 * 1024
+        }
+    }
+}
+
+// request whose header block is parsed and which waits for its body
+#[derive(Debug)]
+struct PendingRequest {
+    request: HttpRequest,
+    framing: BodyFraming,
+    expectation_taken: bool
+}
+
+// keeps the bytes of one connection between reads, bytes that belong
+// to the next request stay buffered after a request is returned
+#[derive(Debug, Default)]
+pub struct RequestParser {
+    buffer: Vec<u8>,
+    pending: Option<PendingRequest>,
+    limits: Limits
+}
+
+impl RequestParser {
+    pub fn new()->Self{
+        Self::with_limits(Limits::default())
+    }
+
+    pub fn with_limits(limits: Limits)->Self{
+        RequestParser { buffer: Vec::new(), pending: None, limits }
+    }
+
+    pub fn feed(&mut self, data: &[u8]){
+        self.buffer.extend_from_slice(data);
+    }
+
+    #[cfg(test)]
+    fn buffered(&self)->&[u8]{
+        &self.buffer
+    }
+
+    // true while a request has been started but isn't complete yet
+    #[cfg(test)]
+    fn is_partial(&self)->bool{
+        self.pending.is_some() || leading_empty_lines_len(&self.buffer) < self.buffer.len()
+    }
+
+    // the head of a request that sent Expect and whose body hasn't arrived,
+    // returned once so the server can answer before the client sends the body
+    pub fn take_expectation(&mut self)->Option<&HttpRequest>{
+        let pending = self.pending.as_mut()?;
+        if pending.expectation_taken || !pending.request.headers.contains_key("Expect") {
+            return None;
+        }
+        pending.expectation_taken = true;
+
+        Some(&pending.request)
+    }
+
+    // Ok(None) means more bytes are needed
+    pub fn next_request(&mut self)->Result<Option<HttpRequest>, ParseError>{
+        if self.pending.is_none() {
+            self.parse_head()?;
+        }
+        let complete = match &mut self.pending {
+            Some(PendingRequest { framing: BodyFraming::Length(length), .. }) => {
+                self.buffer.len() >= *length
+            },
+            Some(PendingRequest { framing: BodyFraming::Chunked(decoder), .. }) => {
+                decoder.decode(&mut self.buffer, &self.limits)?;
+                decoder.is_done()
+            },
+            None => false
+        };
+        if !complete {
+            return Ok(None);
+        }
+        let PendingRequest { mut request, framing, .. } = self.pending.take().expect("pending request checked above");
+        match framing {
+            BodyFraming::Length(length) => {
+                request.body = self.buffer.drain(..length).collect();
+            },
+            BodyFraming::Chunked(decoder) => {
+                let (body, trailers) = decoder.into_parts();
+                // RFC 9112 section 7.1.3, the request now looks like it had a Content-Length
+                request.headers.remove("Transfer-Encoding");
+                request.headers.insert("Content-Length", body.len().to_string());
+                request.body = body;
+                request.trailers = trailers;
+            }
+        }
+
+        Ok(Some(request))
+    }
+
+    fn parse_head(&mut self)->Result<(), ParseError>{
+        let limits = &self.limits;
+        let empty_lines = leading_empty_lines_len(&self.buffer);
+        self.buffer.drain(..empty_lines);
+        // oversized input is refused before it is complete, so it can't pile up in memory
+        let request_line_end = match self.buffer.iter().position(|byte| *byte == b'\n') {
+            Some(end) => end,
+            None if self.buffer.len() > limits.max_request_line => return Err(ParseError::UriTooLong),
+            None => return Ok(())
+        };
+        let request_line = &self.buffer[..request_line_end];
+        if request_line.strip_suffix(b"\r").unwrap_or(request_line).len() > limits.max_request_line {
+            return Err(ParseError::UriTooLong);
+        }
+        let head_end = match find_head_end(&self.buffer) {
+            Some(head_end) => head_end,
+            None if self.buffer.len() - request_line_end > limits.max_header_size => {
+                return Err(ParseError::HeadersTooLarge)
+            },
+            None => return Ok(())
+        };
+        if head_end - request_line_end > limits.max_header_size {
+            return Err(ParseError::HeadersTooLarge);
+        }
+        let request = parse_head(&self.buffer[..head_end])?;
+        if request.headers.len() > limits.max_headers {
+            return Err(ParseError::TooManyHeaders);
+        }
+        let framing = body_framing(&request)?;
+        if matches!(framing, BodyFraming::Length(length) if length > limits.max_body_size) {
+            return Err(ParseError::BodyTooLarge);
+        }
+        self.buffer.drain(..head_end);
+        self.pending = Some(PendingRequest { request, framing, expectation_taken: false });
+
+        Ok(())
+    }
+}
+
+// RFC 9112 section 6.3, only `chunked` is understood as transfer coding
+fn body_framing(request: &HttpRequest)->Result<BodyFraming, ParseError>{
+    let codings: Vec<&str> = request.headers
+        .get_all("Transfer-Encoding")
+        .into_iter()
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .filter(|coding| !coding.is_empty())
+        .collect();
+    if codings.is_empty() {
+        return Ok(BodyFraming::Length(request.content_length()?.unwrap_or(0)));
+    }
+    // both framings at once is a request smuggling vector, so it's refused
+    if request.headers.contains_key("Content-Length") {
+        return Err(ParseError::InvalidTransferEncoding);
+    }
+    let is_chunked = |coding: &&str| coding.eq_ignore_ascii_case("chunked");
+    match codings.split_last() {
+        Some((last, rest)) if is_chunked(last) && !rest.iter().any(is_chunked) => {
+            match rest {
+                [] => Ok(BodyFraming::Chunked(ChunkedDecoder::new())),
+                _ => Err(ParseError::UnsupportedTransferEncoding)
+            }
+        },
+        _ => Err(ParseError::InvalidTransferEncoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_split_across_reads(){
+        let mut parser = RequestParser::new();
+        parser.feed(b"POST /form HTTP/1.1\r\nHo");
+        assert!(parser.next_request().unwrap().is_none());
+        parser.feed(b"st: x\r\nContent-Length: 11\r\n\r\nhello");
+        assert!(parser.next_request().unwrap().is_none());
+        assert!(parser.is_partial());
+        parser.feed(b" worl");
+        assert!(parser.next_request().unwrap().is_none());
+        parser.feed(b"dGET");
+
+        let req = parser.next_request().unwrap().unwrap();
+        assert_eq!(req.path(), "/form");
+        assert_eq!(req.body, b"hello world".to_vec());
+        assert_eq!(parser.buffered(), b"GET");
+    }
+    #[test]
+    fn test_large_body(){
+        let body = vec![b'a'; 64 * 1024];
+        let mut parser = RequestParser::new();
+        parser.feed(format!("POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n", body.len()).as_bytes());
+        for chunk in body.chunks(1000) {
+            assert!(parser.next_request().unwrap().is_none());
+            parser.feed(chunk);
+        }
+
+        let req = parser.next_request().unwrap().unwrap();
+        assert_eq!(req.body, body);
+        assert!(!parser.is_partial());
+    }
+    #[test]
+    fn test_leftover_stays_buffered(){
+        let mut parser = RequestParser::new();
+        parser.feed(b"GET /a HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\n");
+
+        assert_eq!(parser.next_request().unwrap().unwrap().path(), "/a");
+        assert!(parser.next_request().unwrap().is_none());
+        assert_eq!(parser.buffered(), b"GET /b HTTP/1.1\r\n");
+        parser.feed(b"\r\n");
+        assert_eq!(parser.next_request().unwrap().unwrap().path(), "/b");
+        assert!(parser.buffered().is_empty());
+    }
+    #[test]
+    fn test_chunked_body(){
+        let mut parser = RequestParser::new();
+        parser.feed(b"POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\nTrailer: Expires\r\n\r\n4\r\nWiki\r\n");
+        assert!(parser.next_request().unwrap().is_none());
+        parser.feed(b"5;ext=1\r\npedia\r\n0\r\nExpires: never\r\n\r\nGET / HTTP/1.1\r\n\r\n");
+
+        let req = parser.next_request().unwrap().unwrap();
+        assert_eq!(req.body, b"Wikipedia".to_vec());
+        assert_eq!(req.trailers.get("Expires"), Some("never"));
+        assert_eq!(req.headers.get("Transfer-Encoding"), None);
+        assert_eq!(req.content_length(), Ok(Some(9)));
+        assert_eq!(parser.next_request().unwrap().unwrap().path(), "/");
+    }
+    #[test]
+    fn test_transfer_encoding_errors(){
+        let cases = [
+            ("Transfer-Encoding: gzip", ParseError::InvalidTransferEncoding),
+            ("Transfer-Encoding: chunked, chunked", ParseError::InvalidTransferEncoding),
+            ("Transfer-Encoding: gzip, chunked", ParseError::UnsupportedTransferEncoding),
+            ("Transfer-Encoding: chunked\r\nContent-Length: 3", ParseError::InvalidTransferEncoding),
+        ];
+        for (headers, expected) in cases {
+            let mut parser = RequestParser::new();
+            parser.feed(format!("POST / HTTP/1.1\r\n{headers}\r\n\r\n").as_bytes());
+            assert_eq!(parser.next_request().unwrap_err(), expected, "{headers}");
+        }
+    }
+    #[test]
+    fn test_take_expectation(){
+        let mut parser = RequestParser::new();
+        parser.feed(b"PUT /file HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n");
+        assert!(parser.take_expectation().is_none());
+        assert!(parser.next_request().unwrap().is_none());
+        assert_eq!(parser.take_expectation().map(|req| req.path()), Some("/file"));
+        assert!(parser.take_expectation().is_none());
+
+        parser.feed(b"hello");
+        assert_eq!(parser.next_request().unwrap().unwrap().body, b"hello");
+
+        parser.feed(b"PUT /file HTTP/1.1\r\nContent-Length: 5\r\n\r\n");
+        assert!(parser.next_request().unwrap().is_none());
+        assert!(parser.take_expectation().is_none());
+    }
+    #[test]
+    fn test_limits(){
+        let limits = Limits { max_request_line: 32, max_headers: 2, max_header_size: 40, max_body_size: 8 };
+        let long_target = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(32));
+        let cases = [
+            (long_target.as_str(), ParseError::UriTooLong),
+            ("GET /aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", ParseError::UriTooLong),
+            ("GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n", ParseError::TooManyHeaders),
+            ("GET / HTTP/1.1\r\nCookie: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa", ParseError::HeadersTooLarge),
+            ("POST / HTTP/1.1\r\nContent-Length: 9\r\n\r\n", ParseError::BodyTooLarge),
+            ("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n5\r\naaaaa\r\n4\r\n", ParseError::BodyTooLarge),
+        ];
+        for (data, expected) in cases {
+            let mut parser = RequestParser::with_limits(limits);
+            parser.feed(data.as_bytes());
+            assert_eq!(parser.next_request().unwrap_err(), expected, "{data}");
+        }
+
+        let mut parser = RequestParser::with_limits(limits);
+        parser.feed(b"POST / HTTP/1.1\r\nContent-Length: 8\r\n\r\n12345678");
+        assert!(parser.next_request().unwrap().is_some());
+    }
+    #[test]
+    fn test_error_is_reported(){
+        let mut parser = RequestParser::new();
+        parser.feed(b"GET / HTTP/1.1\r\nBroken\r\n\r\n");
+        assert_eq!(parser.next_request().unwrap_err(), ParseError::InvalidHeader);
+    }
+}
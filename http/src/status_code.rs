@@ -0,0 +1,212 @@
+use std::{error::Error, fmt::{self, Display}};
+
+// a three digit status code from 100 to 599 (RFC 9110 section 15)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StatusCode(u16);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InvalidStatusCode;
+
+impl Display for InvalidStatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "status code must be a number from 100 to 599")
+    }
+}
+
+impl Error for InvalidStatusCode {}
+
+// the IANA HTTP Status Code Registry, unassigned and unused codes are left out
+macro_rules! status_codes {
+    ($(($code:literal, $name:ident, $reason:literal),)+) => {
+        impl StatusCode {
+            $(pub const $name: StatusCode = StatusCode($code);)+
+
+            pub fn canonical_reason(&self)->Option<&'static str>{
+                match self.0 {
+                    $($code => Some($reason),)+
+                    _ => None
+                }
+            }
+        }
+    };
+}
+
+status_codes! {
+    (100, CONTINUE, "Continue"),
+    (101, SWITCHING_PROTOCOLS, "Switching Protocols"),
+    (102, PROCESSING, "Processing"),
+    (103, EARLY_HINTS, "Early Hints"),
+    (200, OK, "OK"),
+    (201, CREATED, "Created"),
+    (202, ACCEPTED, "Accepted"),
+    (203, NON_AUTHORITATIVE_INFORMATION, "Non-Authoritative Information"),
+    (204, NO_CONTENT, "No Content"),
+    (205, RESET_CONTENT, "Reset Content"),
+    (206, PARTIAL_CONTENT, "Partial Content"),
+    (207, MULTI_STATUS, "Multi-Status"),
+    (208, ALREADY_REPORTED, "Already Reported"),
+    (226, IM_USED, "IM Used"),
+    (300, MULTIPLE_CHOICES, "Multiple Choices"),
+    (301, MOVED_PERMANENTLY, "Moved Permanently"),
+    (302, FOUND, "Found"),
+    (303, SEE_OTHER, "See Other"),
+    (304, NOT_MODIFIED, "Not Modified"),
+    (305, USE_PROXY, "Use Proxy"),
+    (307, TEMPORARY_REDIRECT, "Temporary Redirect"),
+    (308, PERMANENT_REDIRECT, "Permanent Redirect"),
+    (400, BAD_REQUEST, "Bad Request"),
+    (401, UNAUTHORIZED, "Unauthorized"),
+    (402, PAYMENT_REQUIRED, "Payment Required"),
+    (403, FORBIDDEN, "Forbidden"),
+    (404, NOT_FOUND, "Not Found"),
+    (405, METHOD_NOT_ALLOWED, "Method Not Allowed"),
+    (406, NOT_ACCEPTABLE, "Not Acceptable"),
+    (407, PROXY_AUTHENTICATION_REQUIRED, "Proxy Authentication Required"),
+    (408, REQUEST_TIMEOUT, "Request Timeout"),
+    (409, CONFLICT, "Conflict"),
+    (410, GONE, "Gone"),
+    (411, LENGTH_REQUIRED, "Length Required"),
+    (412, PRECONDITION_FAILED, "Precondition Failed"),
+    (413, CONTENT_TOO_LARGE, "Content Too Large"),
+    (414, URI_TOO_LONG, "URI Too Long"),
+    (415, UNSUPPORTED_MEDIA_TYPE, "Unsupported Media Type"),
+    (416, RANGE_NOT_SATISFIABLE, "Range Not Satisfiable"),
+    (417, EXPECTATION_FAILED, "Expectation Failed"),
+    (421, MISDIRECTED_REQUEST, "Misdirected Request"),
+    (422, UNPROCESSABLE_CONTENT, "Unprocessable Content"),
+    (423, LOCKED, "Locked"),
+    (424, FAILED_DEPENDENCY, "Failed Dependency"),
+    (425, TOO_EARLY, "Too Early"),
+    (426, UPGRADE_REQUIRED, "Upgrade Required"),
+    (428, PRECONDITION_REQUIRED, "Precondition Required"),
+    (429, TOO_MANY_REQUESTS, "Too Many Requests"),
+    (431, REQUEST_HEADER_FIELDS_TOO_LARGE, "Request Header Fields Too Large"),
+    (451, UNAVAILABLE_FOR_LEGAL_REASONS, "Unavailable For Legal Reasons"),
+    (500, INTERNAL_SERVER_ERROR, "Internal Server Error"),
+    (501, NOT_IMPLEMENTED, "Not Implemented"),
+    (502, BAD_GATEWAY, "Bad Gateway"),
+    (503, SERVICE_UNAVAILABLE, "Service Unavailable"),
+    (504, GATEWAY_TIMEOUT, "Gateway Timeout"),
+    (505, HTTP_VERSION_NOT_SUPPORTED, "HTTP Version Not Supported"),
+    (506, VARIANT_ALSO_NEGOTIATES, "Variant Also Negotiates"),
+    (507, INSUFFICIENT_STORAGE, "Insufficient Storage"),
+    (508, LOOP_DETECTED, "Loop Detected"),
+    (510, NOT_EXTENDED, "Not Extended"),
+    (511, NETWORK_AUTHENTICATION_REQUIRED, "Network Authentication Required"),
+}
+
+impl StatusCode {
+    // unregistered codes in the valid range are allowed, they just have no reason phrase
+    pub fn from_u16(code: u16)->Result<StatusCode, InvalidStatusCode>{
+        match code {
+            100..=599 => Ok(StatusCode(code)),
+            _ => Err(InvalidStatusCode)
+        }
+    }
+
+    pub fn as_u16(&self)->u16{
+        self.0
+    }
+
+    pub fn is_informational(&self)->bool{
+        (100..200).contains(&self.0)
+    }
+
+    pub fn is_success(&self)->bool{
+        (200..300).contains(&self.0)
+    }
+
+    pub fn is_redirect(&self)->bool{
+        (300..400).contains(&self.0)
+    }
+
+    pub fn is_client_error(&self)->bool{
+        (400..500).contains(&self.0)
+    }
+
+    pub fn is_server_error(&self)->bool{
+        (500..600).contains(&self.0)
+    }
+
+    // 1xx, 204 and 304 responses never have content (RFC 9112 section 6.3)
+    pub fn allows_body(&self)->bool{
+        !self.is_informational() && *self != StatusCode::NO_CONTENT && *self != StatusCode::NOT_MODIFIED
+    }
+}
+
+impl Default for StatusCode {
+    fn default() -> Self {
+        StatusCode::OK
+    }
+}
+
+impl TryFrom<u16> for StatusCode {
+    type Error = InvalidStatusCode;
+
+    fn try_from(code: u16) -> Result<Self, Self::Error> {
+        StatusCode::from_u16(code)
+    }
+}
+
+impl TryFrom<&str> for StatusCode {
+    type Error = InvalidStatusCode;
+
+    fn try_from(code: &str) -> Result<Self, Self::Error> {
+        if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(InvalidStatusCode);
+        }
+        StatusCode::from_u16(code.parse().map_err(|_| InvalidStatusCode)?)
+    }
+}
+
+impl From<StatusCode> for u16 {
+    fn from(status: StatusCode) -> Self {
+        status.0
+    }
+}
+
+impl Display for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_code_from(){
+        assert_eq!(StatusCode::try_from(404), Ok(StatusCode::NOT_FOUND));
+        assert_eq!(StatusCode::try_from("503"), Ok(StatusCode::SERVICE_UNAVAILABLE));
+        assert_eq!(StatusCode::try_from(299).map(|status| status.as_u16()), Ok(299));
+        for invalid in [0, 99, 600, 1000] {
+            assert_eq!(StatusCode::try_from(invalid), Err(InvalidStatusCode), "{invalid}");
+        }
+        for invalid in ["", "20", "2000", "+20", "abc", "099"] {
+            assert_eq!(StatusCode::try_from(invalid), Err(InvalidStatusCode), "{invalid}");
+        }
+    }
+    #[test]
+    fn test_canonical_reason(){
+        assert_eq!(StatusCode::CREATED.canonical_reason(), Some("Created"));
+        assert_eq!(StatusCode::MOVED_PERMANENTLY.canonical_reason(), Some("Moved Permanently"));
+        assert_eq!(StatusCode::NOT_MODIFIED.canonical_reason(), Some("Not Modified"));
+        assert_eq!(StatusCode::from_u16(299).unwrap().canonical_reason(), None);
+        assert_eq!(StatusCode::from_u16(418).unwrap().canonical_reason(), None);
+    }
+    #[test]
+    fn test_status_categories(){
+        assert!(StatusCode::CONTINUE.is_informational());
+        assert!(StatusCode::NO_CONTENT.is_success());
+        assert!(StatusCode::TEMPORARY_REDIRECT.is_redirect());
+        assert!(StatusCode::NOT_FOUND.is_client_error());
+        assert!(StatusCode::BAD_GATEWAY.is_server_error());
+        assert!(!StatusCode::OK.is_redirect());
+
+        assert!(StatusCode::OK.allows_body());
+        assert!(!StatusCode::SWITCHING_PROTOCOLS.allows_body());
+        assert!(!StatusCode::NOT_MODIFIED.allows_body());
+        assert_eq!(StatusCode::OK.to_string(), "200");
+    }
+}
@@ -0,0 +1,209 @@
+use std::{collections::HashMap, fmt::{self, Display}};
+
+pub type QueryMap = HashMap<String, Vec<String>>;
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Uri {
+    path: String,
+    segments: Vec<String>,
+    query: QueryMap,
+    raw_query: Option<String>
+}
+
+impl Uri {
+    // decoded path with dot segments removed and empty segments collapsed
+    pub fn path(&self)->&str{
+        &self.path
+    }
+
+    pub fn segments(&self)->&[String]{
+        &self.segments
+    }
+
+    pub fn query(&self, key: &str)->Option<&str>{
+        self.query.get(key)?.first().map(String::as_str)
+    }
+
+    pub fn query_all(&self, key: &str)->&[String]{
+        match self.query.get(key) {
+            Some(values) => values,
+            None => &[]
+        }
+    }
+
+    pub fn query_map(&self)->&QueryMap{
+        &self.query
+    }
+
+    pub fn raw_query(&self)->Option<&str>{
+        self.raw_query.as_deref()
+    }
+}
+
+impl From<&str> for Uri {
+    fn from(target: &str) -> Self {
+        let target = target.split('#').next().unwrap_or("");
+        let (raw_path, raw_query) = match target.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (target, None)
+        };
+        let (segments, trailing_slash) = remove_dot_segments(raw_path);
+
+        let mut path = format!("/{}", segments.join("/"));
+        if trailing_slash && !segments.is_empty() {
+            path.push('/');
+        }
+
+        Uri {
+            path,
+            segments,
+            query: raw_query.map(parse_query).unwrap_or_default(),
+            raw_query: raw_query.map(String::from)
+        }
+    }
+}
+
+impl Display for Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut encoded = String::from("/");
+        encoded.push_str(&self.segments
+            .iter()
+            .map(|segment| percent_encode(segment))
+            .collect::<Vec<_>>()
+            .join("/"));
+        if self.path.len() > 1 && self.path.ends_with('/') {
+            encoded.push('/');
+        }
+        match &self.raw_query {
+            Some(query) => write!(f, "{encoded}?{query}"),
+            None => write!(f, "{encoded}")
+        }
+    }
+}
+
+// RFC 3986 section 5.2.4, applied to decoded segments so that
+// encoded dots ("%2e%2e") can't be used to escape the root
+fn remove_dot_segments(raw_path: &str)->(Vec<String>, bool){
+    let mut segments: Vec<String> = Vec::new();
+    let mut trailing_slash = false;
+    for raw_segment in raw_path.split('/').skip(1) {
+        let segment = decode_component(raw_segment, false);
+        trailing_slash = true;
+        match segment.as_str() {
+            "" | "." => {},
+            ".." => {
+                segments.pop();
+            },
+            _ => {
+                segments.push(segment);
+                trailing_slash = false;
+            }
+        }
+    }
+
+    (segments, trailing_slash)
+}
+
+pub fn parse_query(query: &str)->QueryMap{
+    let mut map = QueryMap::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        map.entry(decode_component(key, true))
+            .or_default()
+            .push(decode_component(value, true));
+    }
+
+    map
+}
+
+pub fn decode_component(s: &str, plus_as_space: bool)->String{
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() && is_hex_pair(bytes[i + 1], bytes[i + 2]) => {
+                decoded.push(hex_value(bytes[i + 1]) << 4 | hex_value(bytes[i + 2]));
+                i += 3;
+                continue;
+            },
+            b'+' if plus_as_space => decoded.push(b' '),
+            byte => decoded.push(byte)
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+pub fn percent_encode(s: &str)->String{
+    let mut encoded = String::with_capacity(s.len());
+    for byte in s.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+
+    encoded
+}
+
+fn is_hex_pair(high: u8, low: u8)->bool{
+    high.is_ascii_hexdigit() && low.is_ascii_hexdigit()
+}
+
+fn hex_value(byte: u8)->u8{
+    match byte {
+        b'0'..=b'9' => byte - b'0',
+        b'a'..=b'f' => byte - b'a' + 10,
+        _ => byte - b'A' + 10
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_and_query(){
+        let uri: Uri = "/search?q=a%20b&page=2#results".into();
+        assert_eq!(uri.path(), "/search");
+        assert_eq!(uri.segments(), ["search"]);
+        assert_eq!(uri.query("q"), Some("a b"));
+        assert_eq!(uri.query("page"), Some("2"));
+        assert_eq!(uri.query("missing"), None);
+        assert_eq!(uri.raw_query(), Some("q=a%20b&page=2"));
+    }
+    #[test]
+    fn test_multi_valued_query(){
+        let uri: Uri = "/?tag=a&tag=b+c&empty&=x".into();
+        assert_eq!(uri.query_all("tag"), ["a", "b c"]);
+        assert_eq!(uri.query("empty"), Some(""));
+        assert_eq!(uri.query(""), Some("x"));
+        assert!(uri.query_all("missing").is_empty());
+    }
+    #[test]
+    fn test_remove_dot_segments(){
+        let cases = [
+            ("/a/../b", "/b"),
+            ("//x", "/x"),
+            ("/a/./b/", "/a/b/"),
+            ("/a/b/..", "/a/"),
+            ("/../../etc/passwd", "/etc/passwd"),
+            ("/%2e%2e/secret", "/secret"),
+            ("", "/"),
+            ("/", "/"),
+        ];
+        for (target, expected) in cases {
+            let uri: Uri = target.into();
+            assert_eq!(uri.path(), expected, "target {target}");
+        }
+    }
+    #[test]
+    fn test_percent_decoding(){
+        let uri: Uri = "/caf%C3%A9/a+b/%zz".into();
+        assert_eq!(uri.segments(), ["café", "a+b", "%zz"]);
+        assert_eq!(uri.to_string(), "/caf%C3%A9/a+b/%25zz");
+    }
+}
@@ -1,67 +1,233 @@
-use std::{collections::HashMap, env, fs};
+use std::{env, fs::File, time::SystemTime};
 
 use http::{
-    http_request::{HttpRequest, Resource},
+    body::Body,
+    conditional::Precondition,
+    form::FormError,
+    header_map::HeaderMap,
+    http_request::HttpRequest,
     http_response::HttpResponse,
+    range::RangeSelection,
+    status_code::StatusCode,
+    typed_headers::{EntityTag, LastModified},
 };
 
 pub trait Handler {
     fn handle(req: &HttpRequest) -> HttpResponse;
-    fn load_file(file_name: &str) -> Option<String> {
-        let default_path = format!("{}/public", env!("CARGO_MANIFEST_DIR"));
-        let public_path = env::var("PUBLIC_PATH").unwrap_or(default_path);
-        let full_path = format!("{public_path}/{file_name}");
-
-        fs::read_to_string(full_path).ok()
+    // only regular files, a directory opens fine on some platforms
+    fn open_file(file_name: &str) -> Option<File> {
+        let file = File::open(public_file_path(file_name)).ok()?;
+        file.metadata().ok()?.is_file().then_some(file)
     }
 }
 
+fn public_file_path(file_name: &str) -> String {
+    let default_path = format!("{}/public", env!("CARGO_MANIFEST_DIR"));
+    let public_path = env::var("PUBLIC_PATH").unwrap_or(default_path);
+    format!("{public_path}/{file_name}")
+}
+
 pub struct StaticPageHandler;
 pub struct PageNotFoundHandler;
+pub struct OptionsHandler;
+pub struct TraceHandler;
+pub struct MethodNotAllowedHandler;
+pub struct NotImplementedHandler;
+pub struct FormHandler;
+
+// the one path that takes a request body
+pub const FORM_PATH: &str = "/form";
+
+const ALLOWED_METHODS: &str = "GET, HEAD, OPTIONS, TRACE";
+const FORM_ALLOWED_METHODS: &str = "OPTIONS, POST, TRACE";
+
+pub fn allowed_methods(req: &HttpRequest) -> &'static str {
+    match req.path() == FORM_PATH {
+        true => FORM_ALLOWED_METHODS,
+        false => ALLOWED_METHODS
+    }
+}
+
+// headers that may carry credentials are not reflected back by TRACE
+const TRACE_EXCLUDED_HEADERS: [&str; 3] = ["Authorization", "Proxy-Authorization", "Cookie"];
 
 impl Handler for PageNotFoundHandler {
     fn handle(_: &HttpRequest) -> HttpResponse {
-        HttpResponse::new("404", None, Self::load_file("NotFound.html"))
+        let response = HttpResponse::builder().status(StatusCode::NOT_FOUND);
+        match Self::open_file("NotFound.html").and_then(|file| Body::from_file(file).ok()) {
+            Some(body) => response.header("Content-Type", "text/html").body(body),
+            None => response.build()
+        }
     }
 }
 
-impl Handler for StaticPageHandler {
+impl Handler for OptionsHandler {
+    // `OPTIONS *` gets the methods of the static pages
     fn handle(req: &HttpRequest) -> HttpResponse {
-        match &req.resource {
-            Resource::Path(s) => {
-                let route: Vec<&str> = s.split("/").collect();
-                match route[1] {
-                    "" => HttpResponse::new("200", None, Self::load_file("index.html")),
-                    path => match Self::load_file(path) {
-                        Some(contents) => {
-                            let headers = get_headers_base_on_extension(path);
-                            HttpResponse::new("200", Some(headers), Some(contents))
-                        }
-                        None => PageNotFoundHandler::handle(req)
-                    },
-                }
+        let mut headers = HeaderMap::new();
+        headers.insert("Allow", allowed_methods(req).to_string());
+        HttpResponse::new(StatusCode::OK, Some(headers), None)
+    }
+}
+
+impl Handler for TraceHandler {
+    fn handle(req: &HttpRequest) -> HttpResponse {
+        let mut message = format!(
+            "{} {} {}\r\n",
+            req.method.as_str(),
+            req.resource,
+            req.version.as_str()
+        );
+        for (key, value) in req.headers.iter() {
+            if TRACE_EXCLUDED_HEADERS.iter().any(|excluded| excluded.eq_ignore_ascii_case(key)) {
+                continue;
             }
+            message.push_str(&format!("{key}: {value}\r\n"));
         }
+        message.push_str("\r\n");
+
+        let mut headers = HeaderMap::new();
+        headers.insert("Content-Type", "message/http".to_string());
+        HttpResponse::new(StatusCode::OK, Some(headers), Some(message))
     }
 }
 
-fn get_headers_base_on_extension(file_name: &str) -> HashMap<&str, String> {
-    let mut headers: HashMap<&str, String> = HashMap::new();
-    let key = "Content-Type";
-    match file_name.split('.').last() {
-        Some("css") => {
-            headers.insert(key, "text/css".to_string());
+impl Handler for MethodNotAllowedHandler {
+    fn handle(req: &HttpRequest) -> HttpResponse {
+        let mut headers = HeaderMap::new();
+        headers.insert("Allow", allowed_methods(req).to_string());
+        HttpResponse::new(StatusCode::METHOD_NOT_ALLOWED, Some(headers), None)
+    }
+}
+
+impl Handler for NotImplementedHandler {
+    fn handle(_: &HttpRequest) -> HttpResponse {
+        HttpResponse::new(StatusCode::NOT_IMPLEMENTED, None, None)
+    }
+}
+
+impl Handler for FormHandler {
+    // answers with the posted fields, one `name: value` line each, and the
+    // name, filename and size of every uploaded file
+    fn handle(req: &HttpRequest) -> HttpResponse {
+        let form = match req.form() {
+            Ok(form) => form,
+            Err(e) => {
+                let status = match e {
+                    FormError::UnsupportedContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    FormError::TooManyFields | FormError::FieldTooLarge | FormError::FileTooLarge => StatusCode::CONTENT_TOO_LARGE,
+                    _ => StatusCode::BAD_REQUEST
+                };
+                return HttpResponse::builder().status(status).build();
+            }
+        };
+        let mut names: Vec<&String> = form.fields().keys().collect();
+        names.sort();
+        let mut text = String::new();
+        for name in names {
+            for value in form.get_all(name) {
+                text.push_str(&format!("{name}: {value}\n"));
+            }
+        }
+        for file in form.files() {
+            text.push_str(&format!("{}: {} ({} bytes)\n", file.name, file.filename, file.data.len()));
+        }
+        HttpResponse::text(text)
+    }
+}
+
+impl Handler for StaticPageHandler {
+    fn handle(req: &HttpRequest) -> HttpResponse {
+        let segments = req.segments();
+        // decoded segments may still smuggle separators, e.g. "..%2F"
+        if segments.iter().any(|segment| segment.contains(['/', '\\', '\0'])) {
+            return PageNotFoundHandler::handle(req);
+        }
+        let path = match segments.is_empty() {
+            true => "index.html".to_string(),
+            false => segments.join("/")
+        };
+        let Some(file) = Self::open_file(&path) else {
+            return PageNotFoundHandler::handle(req);
+        };
+        let Ok(metadata) = file.metadata() else {
+            return PageNotFoundHandler::handle(req);
+        };
+        let modified = metadata.modified().ok();
+        let etag = modified.map(|modified| EntityTag::from_metadata(metadata.len(), modified));
+
+        let mut headers = get_headers_base_on_extension(&path);
+        headers.insert("Accept-Ranges", "bytes");
+        if let Some(etag) = &etag {
+            headers.insert_typed(etag);
         }
-        Some("js") => {
-            headers.insert(key, "text/javascript".to_string());
+        if let Some(modified) = modified {
+            headers.insert_typed(&LastModified(modified));
         }
-        Some("html") => {
-            headers.insert(key, "text/html".to_string());
+        // validators come from metadata, so an unchanged file isn't read at all
+        match req.evaluate_preconditions(etag.as_ref(), modified) {
+            // the router trims this to a 304 once compression has set its Vary and ETag
+            Precondition::NotModified => {
+                return HttpResponse::builder()
+                    .status(StatusCode::NOT_MODIFIED)
+                    .headers(headers)
+                    .body(Body::File(file, metadata.len()));
+            },
+            Precondition::Failed => return HttpResponse::precondition_failed(),
+            Precondition::Proceed => {}
         }
-        _ => {
-            headers.insert(key, "text/plain".to_string());
+        Self::file_response(req, headers, file, metadata.len(), etag.as_ref(), modified)
+    }
+}
+
+impl StaticPageHandler {
+    fn file_response(
+        req: &HttpRequest,
+        headers: HeaderMap,
+        file: File,
+        len: u64,
+        etag: Option<&EntityTag>,
+        modified: Option<SystemTime>
+    ) -> HttpResponse {
+        match req.range_selection(len, etag, modified) {
+            RangeSelection::Full => HttpResponse::builder().headers(headers).body(Body::File(file, len)),
+            RangeSelection::Partial(ranges) => HttpResponse::partial_content(headers, file, len, &ranges)
+                .unwrap_or_else(|_| HttpResponse::builder().status(StatusCode::INTERNAL_SERVER_ERROR).build()),
+            RangeSelection::Unsatisfiable => HttpResponse::range_not_satisfiable(headers, len)
         }
     }
+}
+
+fn get_headers_base_on_extension(file_name: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let extension = file_name.rsplit_once('.').map(|(_, extension)| extension.to_ascii_lowercase());
+    let content_type = match extension.as_deref() {
+        Some("html" | "htm") => "text/html",
+        Some("css") => "text/css",
+        Some("js" | "mjs") => "text/javascript",
+        Some("txt") => "text/plain",
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("ttf") => "font/ttf",
+        Some("otf") => "font/otf",
+        Some("pdf") => "application/pdf",
+        Some("wasm") => "application/wasm",
+        Some("zip") => "application/zip",
+        Some("mp3") => "audio/mpeg",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        // unknown files are downloaded rather than shown as text
+        _ => "application/octet-stream"
+    };
+    headers.insert("Content-Type", content_type);
 
     headers
 }
@@ -1,14 +1,34 @@
+use std::process;
 
 
+
+use http::{compression::Compression, request_parser::Limits};
+use redirect::RedirectTable;
 use server::Server;
 
 mod server;
+mod redirect;
 mod router;
 mod handler;
 mod web_socket;
 
 fn main() {
-    let server = Server::new("127.0.0.1:8080");
+    let limits = Limits {
+        max_body_size: 1024 * 1024,
+        ..Limits::default()
+    };
+    let redirects = match RedirectTable::load() {
+        Ok(redirects) => redirects,
+        Err(e) => {
+            eprintln!("invalid redirect table {e}");
+            process::exit(1);
+        }
+    };
+    let server = Server::new("127.0.0.1:8080")
+        .limits(limits)
+        .compression(Compression::default())
+        .server_header(concat!("http_server/", env!("CARGO_PKG_VERSION")))
+        .redirects(redirects);
     server.listen();
  }
  
\ No newline at end of file
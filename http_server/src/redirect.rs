@@ -0,0 +1,191 @@
+use std::{env, fs, io::ErrorKind};
+
+use http::{
+    http_request::HttpRequest,
+    http_response::HttpResponse,
+    status_code::StatusCode,
+    uri::percent_encode
+};
+
+// rules without a status move the page for good
+const DEFAULT_STATUS: StatusCode = StatusCode::MOVED_PERMANENTLY;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Pattern {
+    Exact(String),
+    // `/old/*` matches every path under `/old/`, the rest replaces `$1`
+    Prefix(String)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RedirectRule {
+    pattern: Pattern,
+    target: String,
+    status: StatusCode
+}
+
+// moved pages, checked in file order before a request is routed
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RedirectTable {
+    rules: Vec<RedirectRule>
+}
+
+impl RedirectTable {
+    // REDIRECTS_PATH names the file, without it `redirects.conf` next to `public/`
+    // is used, and a missing default file means no redirects
+    pub fn load()->Result<RedirectTable, String>{
+        let (path, required) = match env::var("REDIRECTS_PATH") {
+            Ok(path) => (path, true),
+            Err(_) => (format!("{}/redirects.conf", env!("CARGO_MANIFEST_DIR")), false)
+        };
+        match fs::read_to_string(&path) {
+            Ok(config) => RedirectTable::parse(&config).map_err(|e| format!("{path}: {e}")),
+            Err(e) if e.kind() == ErrorKind::NotFound && !required => Ok(RedirectTable::default()),
+            Err(e) => Err(format!("{path}: {e}"))
+        }
+    }
+
+    // one rule per line, `from to [status]`, lines starting with `#` are comments:
+    //   /about.html /about
+    //   /old/* /new/$1 308
+    pub fn parse(config: &str)->Result<RedirectTable, String>{
+        let mut rules = Vec::new();
+        for (index, line) in config.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = parse_rule(line).map_err(|e| format!("line {}: {e}", index + 1))?;
+            rules.push(rule);
+        }
+
+        Ok(RedirectTable { rules })
+    }
+
+    pub fn redirect(&self, req: &HttpRequest)->Option<HttpResponse>{
+        let uri = req.uri()?;
+        let path = uri.path();
+        let rule_target = self.rules.iter().find_map(|rule| {
+            let target = match &rule.pattern {
+                Pattern::Exact(from) if from == path => rule.target.clone(),
+                Pattern::Prefix(prefix) => {
+                    let rest = path.strip_prefix(prefix.as_str())?;
+                    // the path is decoded, so the rest is encoded again segment by segment
+                    let rest = rest.split('/').map(percent_encode).collect::<Vec<_>>().join("/");
+                    rule.target.replace("$1", &rest)
+                },
+                Pattern::Exact(_) => return None
+            };
+            Some((rule, target))
+        });
+        let (rule, mut target) = rule_target?;
+        // the query goes along unless the target has its own
+        if let Some(query) = uri.raw_query().filter(|_| !target.contains('?')) {
+            target = format!("{target}?{query}");
+        }
+
+        Some(HttpResponse::redirect_with_status(rule.status, target))
+    }
+}
+
+fn parse_rule(line: &str)->Result<RedirectRule, String>{
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let (from, target, status) = match parts[..] {
+        [from, target] => (from, target, DEFAULT_STATUS),
+        [from, target, status] => {
+            let status = StatusCode::try_from(status)
+                .ok()
+                .filter(|status| matches!(status.as_u16(), 301 | 302 | 303 | 307 | 308))
+                .ok_or(format!("{status} is not a redirect status"))?;
+            (from, target, status)
+        },
+        _ => return Err(format!("expected `from to [status]`, found `{line}`"))
+    };
+    if !from.starts_with('/') {
+        return Err(format!("`{from}` is not a path"));
+    }
+    // the target ends up in the Location header
+    if target.bytes().any(|byte| byte.is_ascii_control()) {
+        return Err(format!("`{target}` is not a valid location"));
+    }
+    let pattern = match from.strip_suffix('*') {
+        Some(prefix) if prefix.ends_with('/') && !prefix.contains('*') => Pattern::Prefix(prefix.to_string()),
+        Some(_) => return Err(format!("`{from}` can only end with `/*`")),
+        None if from.contains('*') => return Err(format!("`{from}` can only end with `/*`")),
+        None => Pattern::Exact(from.to_string())
+    };
+    if matches!(pattern, Pattern::Exact(_)) && target.contains("$1") {
+        return Err(format!("`{target}` uses $1 but `{from}` has no `*`"));
+    }
+
+    Ok(RedirectRule { pattern, target: target.to_string(), status })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redirect(table: &RedirectTable, target: &str)->Option<(StatusCode, String)>{
+        let req = HttpRequest::parse(format!("GET {target} HTTP/1.1\r\n\r\n").as_bytes()).unwrap();
+        let response = table.redirect(&req)?;
+        Some((response.status(), response.headers().get("Location")?.to_string()))
+    }
+
+    #[test]
+    fn test_parse_rules(){
+        let table = RedirectTable::parse("# moved pages\n\n  /home /  \n/old/* /new/$1 308\n").unwrap();
+        assert_eq!(table.rules, vec![
+            RedirectRule { pattern: Pattern::Exact("/home".to_string()), target: "/".to_string(), status: StatusCode::MOVED_PERMANENTLY },
+            RedirectRule { pattern: Pattern::Prefix("/old/".to_string()), target: "/new/$1".to_string(), status: StatusCode::PERMANENT_REDIRECT },
+        ]);
+        assert_eq!(RedirectTable::parse(""), Ok(RedirectTable::default()));
+    }
+    #[test]
+    fn test_parse_invalid_rules(){
+        let cases = [
+            "/a /b 200",
+            "/a /b 404",
+            "/a /b abc",
+            "/a",
+            "/a /b 301 extra",
+            "a /b",
+            "/a*/b /c",
+            "/a* /c",
+            "/*/a /c",
+            "/a/*/* /c",
+            "/a /c/$1",
+            "/a /c\u{7}",
+        ];
+        for line in cases {
+            assert!(parse_rule(line).is_err(), "{line}");
+        }
+        assert_eq!(
+            RedirectTable::parse("/a /b\n/c /d 200\n"),
+            Err("line 2: 200 is not a redirect status".to_string())
+        );
+    }
+    #[test]
+    fn test_exact_redirect(){
+        let table = RedirectTable::parse("/home / 302\n").unwrap();
+        assert_eq!(redirect(&table, "/home"), Some((StatusCode::FOUND, "/".to_string())));
+        assert_eq!(redirect(&table, "/home/"), None);
+        assert_eq!(redirect(&table, "/homepage"), None);
+    }
+    #[test]
+    fn test_prefix_redirect(){
+        let table = RedirectTable::parse("/old/* /new/$1 308\n/old/* /unused/$1\n").unwrap();
+        assert_eq!(redirect(&table, "/old/a/b"), Some((StatusCode::PERMANENT_REDIRECT, "/new/a/b".to_string())));
+        assert_eq!(redirect(&table, "/old/"), Some((StatusCode::PERMANENT_REDIRECT, "/new/".to_string())));
+        assert_eq!(redirect(&table, "/old"), None);
+        assert_eq!(redirect(&table, "/older/a"), None);
+        // the decoded rest is encoded again
+        assert_eq!(redirect(&table, "/old/a%20b/c%3Fd"), Some((StatusCode::PERMANENT_REDIRECT, "/new/a%20b/c%3Fd".to_string())));
+    }
+    #[test]
+    fn test_redirect_keeps_query(){
+        let table = RedirectTable::parse("/a /b\n/c /d?from=c\n").unwrap();
+        assert_eq!(redirect(&table, "/a?x=1&y=2"), Some((DEFAULT_STATUS, "/b?x=1&y=2".to_string())));
+        assert_eq!(redirect(&table, "/c?x=1"), Some((DEFAULT_STATUS, "/d?from=c".to_string())));
+        assert_eq!(redirect(&table, "/c"), Some((DEFAULT_STATUS, "/d?from=c".to_string())));
+    }
+}
@@ -1,19 +1,79 @@
-use std::{io::Write};
+use http::{compression::Compression, http_request::{HttpRequest, Method}, http_response::HttpResponse, status_code::StatusCode};
 
-use http::http_request::{HttpRequest, Method};
-
-use crate::handler::{StaticPageHandler, PageNotFoundHandler, Handler};
+use crate::handler::{
+    StaticPageHandler, Handler, OptionsHandler, TraceHandler,
+    MethodNotAllowedHandler, NotImplementedHandler, FormHandler, FORM_PATH
+};
 
 pub struct Router;
 
 impl Router{
-    pub fn route(req: HttpRequest, mut stream: &mut impl Write){
-        let mut response = match req.method {
-            Method::Get => {
-                StaticPageHandler::handle(&req)
+    // rejects a request from its head alone, before its body is read
+    pub fn check(req: &HttpRequest)->Option<HttpResponse>{
+        let form = req.path() == FORM_PATH;
+        match req.method {
+            Method::Post if form => None,
+            Method::Get | Method::Head if form => Some(MethodNotAllowedHandler::handle(req)),
+            Method::Post | Method::Put | Method::Delete | Method::Patch | Method::Connect => {
+                Some(MethodNotAllowedHandler::handle(req))
+            }
+            Method::Extension(_) => Some(NotImplementedHandler::handle(req)),
+            Method::Get | Method::Head | Method::Options | Method::Trace => None
+        }
+    }
+
+    // the handler's response, compressed when the client and its type allow it
+    pub fn route(req: &HttpRequest, compression: &Compression)->HttpResponse{
+        let mut response = match Self::check(req) {
+            Some(response) => response,
+            None => match req.method {
+                Method::Options => OptionsHandler::handle(req),
+                Method::Trace => TraceHandler::handle(req),
+                Method::Post => FormHandler::handle(req),
+                _ => StaticPageHandler::handle(req)
             }
-            _=>PageNotFoundHandler::handle(&req)
         };
-        let _ = response.send_response(&mut stream);
+        if response.compress(req, compression).is_err() {
+            response = HttpResponse::builder().status(StatusCode::INTERNAL_SERVER_ERROR).build();
+        }
+        // only the headers a 304 repeats from its 200 are kept
+        if response.status() == StatusCode::NOT_MODIFIED {
+            response = HttpResponse::not_modified(response.headers());
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(headers: &str, compression: &Compression)->HttpResponse{
+        let req = HttpRequest::parse(format!("GET /index.html HTTP/1.1\r\n{headers}\r\n").as_bytes()).unwrap();
+        Router::route(&req, compression)
+    }
+
+    #[test]
+    fn test_not_modified_matches_compressed_response(){
+        let compression = Compression { min_size: 0, ..Compression::default() };
+        let full = get("Accept-Encoding: gzip\r\n", &compression);
+        assert_eq!(full.status(), StatusCode::OK);
+        assert_eq!(full.headers().get("Content-Encoding"), Some("gzip"));
+        let etag = full.headers().get("ETag").unwrap();
+        assert!(etag.starts_with("W/"));
+
+        let not_modified = get(&format!("Accept-Encoding: gzip\r\nIf-None-Match: {etag}\r\n"), &compression);
+        assert_eq!(not_modified.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(not_modified.headers().get("ETag"), Some(etag));
+        assert_eq!(not_modified.headers().get("Vary"), Some("Accept-Encoding"));
+        assert!(not_modified.body().is_none());
+        assert!(!not_modified.headers().contains_key("Content-Type"));
+
+        // without Accept-Encoding the 304 still varies, but keeps the strong tag
+        let strong = etag.trim_start_matches("W/");
+        let not_modified = get(&format!("If-None-Match: {strong}\r\n"), &compression);
+        assert_eq!(not_modified.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(not_modified.headers().get("ETag"), Some(strong));
+        assert_eq!(not_modified.headers().get("Vary"), Some("Accept-Encoding"));
     }
-}
\ No newline at end of file
+}
@@ -1,11 +1,18 @@
-use std::{net::{TcpListener, TcpStream, SocketAddr}, io::{Read, ErrorKind, Write, Error}, time::{ Duration, Instant}, sync::{Arc, Mutex}, thread, collections::{LinkedList}};
-use http::{http_request::{HttpRequest}};
+use std::{net::{TcpListener, TcpStream, SocketAddr, Shutdown}, io::{Read, ErrorKind, Write}, time::{ Duration, Instant}, sync::{Arc, Mutex}, thread, collections::LinkedList};
+use http::{compression::Compression, header_map::HeaderMap, http_date::http_date_now, http_request::{HttpRequest, Method, ParseError, Version}, http_response::HttpResponse, request_parser::{Limits, RequestParser}, status_code::StatusCode};
+use crate::redirect::RedirectTable;
 use crate::web_socket::{handle_web_socket_upgrade, WebSocketConnections, read_web_socket_message};
 
 use super::router::Router;
 
 pub struct Connection{
     stream: TcpStream,
+    parser: RequestParser,
+    compression: Compression,
+    server_header: Option<Arc<str>>,
+    redirects: Arc<RedirectTable>,
+    // set once the response is out and only the client's leftovers are read
+    drain_deadline: Option<Instant>,
     last_time: Instant
 }
 
@@ -13,24 +20,34 @@ impl Connection {
     pub fn get_source_address(&self)->SocketAddr{
         self.stream.peer_addr().expect("No peer address error")
     }
-
-    pub fn write(&mut self, data: &[u8])-> Result<(), Error>{
-        self.stream.write(data)?;
-        self.stream.flush()
-    }
 }
 
+#[derive(Debug, PartialEq)]
 enum ConnectionStatus{
     Close,
+    Draining,
     Open,
     Handled,
     SocketUpgrade
 }
 
 impl Connection{
-    pub fn new(stream: TcpStream)->Self{
+    pub fn new(
+        stream: TcpStream,
+        limits: Limits,
+        compression: Compression,
+        server_header: Option<Arc<str>>,
+        redirects: Arc<RedirectTable>
+    )->Self{
+        // writes block, so a client that stops reading can't hold a worker forever
+        let _ = stream.set_write_timeout(Some(Duration::from_secs(KEEP_ALIVE_TIME)));
         Connection {
             stream,
+            parser: RequestParser::with_limits(limits),
+            compression,
+            server_header,
+            redirects,
+            drain_deadline: None,
             last_time:Instant::now()
         }
     }
@@ -42,6 +59,10 @@ impl Connection{
 
 pub struct Server<'a>{
     socket_address: &'a str,
+    limits: Limits,
+    compression: Compression,
+    server_header: Option<Arc<str>>,
+    redirects: Arc<RedirectTable>,
     connections: Arc<Mutex<LinkedList<Connection>>>,
     web_socket_connections: Arc<Mutex<WebSocketConnections>>
 }
@@ -54,10 +75,35 @@ impl <'a> Server<'a>{
     pub fn new(socket_address:  &'a str)->Self{
         Server {
             socket_address, 
+            limits: Limits::default(),
+            compression: Compression::default(),
+            server_header: None,
+            redirects: Arc::new(RedirectTable::default()),
             connections: Arc::new(Mutex::new(LinkedList::new())),
             web_socket_connections: Arc::new(Mutex::new(WebSocketConnections::new()))
         }
     }
+
+    pub fn limits(mut self, limits: Limits)->Self{
+        self.limits = limits;
+        self
+    }
+
+    pub fn compression(mut self, compression: Compression)->Self{
+        self.compression = compression;
+        self
+    }
+
+    // the Server header of every response, without it none is sent
+    pub fn server_header(mut self, server_header: impl Into<String>)->Self{
+        self.server_header = Some(Arc::from(server_header.into()));
+        self
+    }
+
+    pub fn redirects(mut self, redirects: RedirectTable)->Self{
+        self.redirects = Arc::new(redirects);
+        self
+    }
     
 
     fn set_worker_threads(&self){
@@ -73,14 +119,11 @@ impl <'a> Server<'a>{
                     let mut connection_ptr = ws_connection;
                     let connection = Arc::get_mut(&mut connection_ptr).expect("Failed to get mut");
                     let status = handle_web_socket_connection(&mut connection.stream);
-                    match status {
-                        ConnectionStatus::Open => {
-                            ws_connections
-                            .lock()
-                            .expect("Mutec lock failed")
-                            .insert(&connection_ptr);
-                        },
-                        _=>{}
+                    if let ConnectionStatus::Open = status {
+                        ws_connections
+                        .lock()
+                        .expect("Mutec lock failed")
+                        .insert(&connection_ptr);
                     }
                 }
 
@@ -100,9 +143,22 @@ impl <'a> Server<'a>{
                     }
                 };
 
-                let connection_status = handle_connection(&mut connection.stream);
+                // a closing connection goes around the list until the client is
+                // done sending, instead of holding the worker while it waits
+                if let Some(deadline) = connection.drain_deadline {
+                    if drain(&mut connection.stream, deadline) {
+                        connections.lock().unwrap().push_front(connection);
+                    }
+                    continue;
+                }
+
+                let connection_status = handle_connection(&mut connection);
                 match connection_status {
                     ConnectionStatus::Close => continue,
+                    ConnectionStatus::Draining => {
+                        connection.last_time = Instant::now();
+                        connection.drain_deadline = Some(connection.last_time + CLOSE_DRAIN_TIME);
+                    },
                     ConnectionStatus::Handled=>{connection.last_time = Instant::now();},
                     
                     ConnectionStatus::SocketUpgrade=>{
@@ -127,46 +183,201 @@ impl <'a> Server<'a>{
         println!("Listening on {}", self.socket_address);
         for stream in tcp_listener.incoming(){
             let stream = stream.unwrap();
-            self.connections.lock().unwrap().push_back(Connection::new(stream));
+            self.connections.lock().unwrap().push_back(Connection::new(stream, self.limits, self.compression, self.server_header.clone(), Arc::clone(&self.redirects)));
         }
             
     }
 }
             
 
-fn handle_connection(stream: &mut TcpStream)->ConnectionStatus{
-    stream.set_nonblocking(true).unwrap();
+const READ_BUFFER_SIZE: usize = 4096;
+
+fn handle_connection(connection: &mut Connection)->ConnectionStatus{
+    let Connection { stream, parser, compression, server_header, redirects, .. } = connection;
     let ip = stream.peer_addr().unwrap();
-    let mut read_buffer = vec![0; 1024];
-    let size = stream.read(&mut read_buffer);
-    let _ = match size {
-      Ok(0)=>return ConnectionStatus::Close,  
-      Ok(size)=>size,  
-      Err(e) if e.kind() == ErrorKind::WouldBlock => {
-     
-        return ConnectionStatus::Open
+    let mut read_buffer = [0; READ_BUFFER_SIZE];
+    let mut status = ConnectionStatus::Open;
+    loop {
+        // answer every request that is already buffered, in the order they arrived
+        loop {
+            let req = match parser.next_request() {
+                Ok(Some(req)) => req,
+                Ok(None) => break,
+                Err(e) => {
+                    println!("{e}");
+                    let _ = stream.set_nonblocking(false);
+                    write_response(stream, parse_error_response(&e), None, server_header.as_deref());
+                    return close_gracefully(stream);
+                }
+            };
+            println!("connection - {ip}");
+            // responses are written in full before the next request is read
+            stream.set_nonblocking(false).unwrap();
+
+            //check if request is web socket handshake
+            let ws_result = handle_web_socket_upgrade(&req, stream);
+            if let Err(s) = ws_result {
+                println!("{s}");
+            }else{
+                stream.set_nonblocking(true).unwrap();
+                return ConnectionStatus::SocketUpgrade;
+            }
+
+            if !req.version.is_supported() {
+                write_response(stream, parse_error_response(&ParseError::UnsupportedVersion), None, server_header.as_deref());
+                return close_gracefully(stream);
+            }
+            if req.has_unsupported_expectation() {
+                write_response(stream, expectation_failed_response(), Some(&req), server_header.as_deref());
+                return close_gracefully(stream);
+            }
+
+            // moved pages are answered before any handler sees the request
+            let response = match redirects.redirect(&req) {
+                Some(response) => response,
+                None => Router::route(&req, compression)
+            };
+            if !write_response(stream, response, Some(&req), server_header.as_deref()) {
+                return close_gracefully(stream);
+            }
+            status = ConnectionStatus::Handled;
         }
-        Err(e)=>{
-            println!("{}", e);
-            return ConnectionStatus::Close
+
+        // a client that sent Expect waits for our answer before sending the body
+        if let Some(req) = parser.take_expectation() {
+            stream.set_nonblocking(false).unwrap();
+            if let Some(mut response) = expectation_rejection(req) {
+                response.headers_mut().insert("Connection", "close");
+                write_response(stream, response, Some(req), server_header.as_deref());
+                return close_gracefully(stream);
+            }
+            if req.expects_continue() && stream.write_all(CONTINUE_RESPONSE).is_err() {
+                return ConnectionStatus::Close;
+            }
         }
-    };
-    println!("connection - {ip}");
-    let req: HttpRequest = String::from_utf8(read_buffer.to_vec()).unwrap().trim_matches(char::from(0)).into();
+
+        stream.set_nonblocking(true).unwrap();
+        let size = stream.read(&mut read_buffer);
+        match size {
+            Ok(0)=>return ConnectionStatus::Close,
+            Ok(size)=>parser.feed(&read_buffer[..size]),
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                return status
+            }
+            Err(e)=>{
+                println!("{}", e);
+                return ConnectionStatus::Close
+            }
+        }
+    }
+}
     
-    //check if request is web socket handshake
-    let ws_result = handle_web_socket_upgrade(&req, stream);
-    if let Err(s) = ws_result {
-        println!("{s}");
-    }else{
-        return ConnectionStatus::SocketUpgrade;
+
+// adds the headers every response carries and writes it, the result says
+// whether the connection stays open afterwards, which is what Connection announced
+fn write_response(stream: &mut TcpStream, mut response: HttpResponse, req: Option<&HttpRequest>, server_header: Option<&str>)->bool{
+    // a handler that produced a field which could split the response gets a 500 instead
+    if response.headers().validate().is_err() {
+        response = HttpResponse::builder().status(StatusCode::INTERNAL_SERVER_ERROR).build();
+    }
+    if let Some(req) = req {
+        response.set_version(&req.version);
+    }
+    let headers = response.headers_mut();
+    if !headers.contains_key("Date") {
+        headers.insert("Date", http_date_now());
+    }
+    if let Some(server_header) = server_header.filter(|_| !headers.contains_key("Server")) {
+        headers.insert("Server", server_header);
     }
 
-    Router::route(req, stream);
+    let keep_alive = set_connection_headers(&mut response, req);
+    let sent = match req {
+        Some(req) if req.method == Method::Head => response.send_head(stream),
+        _ => response.send_response(stream)
+    };
+    // a body cut short can't be told apart from the next response
+    sent.is_ok() && keep_alive
+}
+
+// decides whether the connection outlives the response and says so in its
+// Connection and Keep-Alive headers, no request means it can't
+fn set_connection_headers(response: &mut HttpResponse, req: Option<&HttpRequest>)->bool{
+    let version = response.version().clone();
+    // HTTP/1.0 has no chunked coding, a body without a length ends with the connection
+    let close_delimited = version != Version::V1_1 && response.body().is_some_and(|body| body.len().is_none());
+    let keep_alive = req.is_some_and(HttpRequest::keep_alive) && !response.closes_connection() && !close_delimited;
+    let headers = response.headers_mut();
+    if !keep_alive {
+        headers.remove("Keep-Alive");
+        headers.insert("Connection", "close");
+    } else if version == Version::V1_0 {
+        headers.insert("Connection", "keep-alive");
+        headers.insert("Keep-Alive", format!("timeout={KEEP_ALIVE_TIME}"));
+    }
 
-    return ConnectionStatus::Handled
+    keep_alive
+}
+
+fn parse_error_response(error: &ParseError)->HttpResponse{
+    let status = match error {
+        ParseError::UnsupportedVersion => StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+        ParseError::UnsupportedTransferEncoding => StatusCode::NOT_IMPLEMENTED,
+        ParseError::UriTooLong => StatusCode::URI_TOO_LONG,
+        ParseError::TooManyHeaders | ParseError::HeadersTooLarge => StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+        ParseError::BodyTooLarge => StatusCode::CONTENT_TOO_LARGE,
+        _ => StatusCode::BAD_REQUEST
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert("Connection", "close".to_string());
+
+    HttpResponse::new(status, Some(headers), None)
+}
+
+// interim responses carry no Content-Length, so this isn't built with HttpResponse
+const CONTINUE_RESPONSE: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n";
+
+// a final response for a request whose body the client is holding back
+fn expectation_rejection(req: &HttpRequest)->Option<HttpResponse>{
+    if !req.version.is_supported() {
+        return Some(parse_error_response(&ParseError::UnsupportedVersion));
+    }
+    if req.has_unsupported_expectation() {
+        return Some(expectation_failed_response());
+    }
+
+    Router::check(req)
+}
+
+fn expectation_failed_response()->HttpResponse{
+    let mut headers = HeaderMap::new();
+    headers.insert("Connection", "close".to_string());
+
+    HttpResponse::new(StatusCode::EXPECTATION_FAILED, Some(headers), None)
+}
+
+const CLOSE_DRAIN_TIME: Duration = Duration::from_millis(500);
+
+// the client may still be sending the rest of a rejected request, closing with
+// unread data makes the OS reset the connection and the response can be lost
+fn close_gracefully(stream: &mut TcpStream)->ConnectionStatus{
+    if stream.shutdown(Shutdown::Write).is_err() || stream.set_nonblocking(true).is_err() {
+        return ConnectionStatus::Close;
+    }
+    ConnectionStatus::Draining
+}
+
+// one read per pass over the connections, false once the client is done or out of time
+fn drain(stream: &mut TcpStream, deadline: Instant)->bool{
+    if Instant::now() >= deadline {
+        return false;
+    }
+    let mut drain_buffer = [0; READ_BUFFER_SIZE];
+    match stream.read(&mut drain_buffer) {
+        Ok(size) => size > 0,
+        Err(e) => matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted)
+    }
 }
-    
 
 fn handle_web_socket_connection(stream: &mut TcpStream)->ConnectionStatus{
     let mut read_buffer = [0; 1024];
@@ -191,3 +402,191 @@ fn handle_web_socket_connection(stream: &mut TcpStream)->ConnectionStatus{
     ConnectionStatus::Open
 }
 
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use http::body::Body;
+
+    use super::*;
+
+    fn connection_pair(limits: Limits, redirects: RedirectTable)->(Connection, TcpStream){
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        client.set_read_timeout(Some(Duration::from_millis(100))).unwrap();
+        (Connection::new(stream, limits, Compression::default(), None, Arc::new(redirects)), client)
+    }
+
+    // sends `data`, lets the connection answer what arrived and returns what
+    // the client read until the connection closed or went quiet
+    fn exchange(connection: &mut Connection, client: &mut TcpStream, data: &[u8])->(ConnectionStatus, String){
+        client.write_all(data).unwrap();
+        connection.stream.set_nonblocking(false).unwrap();
+        connection.stream.peek(&mut [0]).unwrap();
+        let status = handle_connection(connection);
+
+        let mut output = Vec::new();
+        let mut buffer = [0; READ_BUFFER_SIZE];
+        loop {
+            match client.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(size) => output.extend_from_slice(&buffer[..size])
+            }
+        }
+        (status, String::from_utf8_lossy(&output).into_owned())
+    }
+
+    fn index_len()->u64{
+        fs::metadata(format!("{}/public/index.html", env!("CARGO_MANIFEST_DIR"))).unwrap().len()
+    }
+
+    #[test]
+    fn test_pipelined_requests(){
+        let (mut connection, mut client) = connection_pair(Limits::default(), RedirectTable::default());
+        let requests = b"GET /index.html HTTP/1.1\r\n\r\nGET /missing.html HTTP/1.1\r\n\r\nOPTIONS * HTTP/1.1\r\n\r\n";
+        let (status, output) = exchange(&mut connection, &mut client, requests);
+        assert_eq!(status, ConnectionStatus::Handled);
+        let statuses: Vec<&str> = output.match_indices("HTTP/1.1 ").map(|(index, _)| &output[index + 9..index + 12]).collect();
+        assert_eq!(statuses, ["200", "404", "200"]);
+        assert!(output.rsplit("HTTP/1.1 ").next().unwrap().contains("Allow: GET, HEAD, OPTIONS, TRACE\r\n"));
+
+        // the connection stays usable for the next batch
+        let (status, output) = exchange(&mut connection, &mut client, b"GET / HTTP/1.1\r\n\r\n");
+        assert_eq!(status, ConnectionStatus::Handled);
+        assert!(output.starts_with("HTTP/1.1 200 OK\r\n"));
+    }
+    #[test]
+    fn test_head_has_no_body(){
+        let (mut connection, mut client) = connection_pair(Limits::default(), RedirectTable::default());
+        let (status, output) = exchange(&mut connection, &mut client, b"HEAD /index.html HTTP/1.1\r\n\r\n");
+        assert_eq!(status, ConnectionStatus::Handled);
+        assert!(output.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(output.contains(&format!("Content-Length: {}\r\n", index_len())));
+        assert!(output.ends_with("\r\n\r\n"));
+    }
+    #[test]
+    fn test_unsupported_version(){
+        let (mut connection, mut client) = connection_pair(Limits::default(), RedirectTable::default());
+        let (status, output) = exchange(&mut connection, &mut client, b"GET / HTTP/2.0\r\n\r\n");
+        assert_eq!(status, ConnectionStatus::Draining);
+        assert!(output.starts_with("HTTP/1.1 505 HTTP Version Not Supported\r\n"));
+        assert!(output.contains("Connection: close\r\n"));
+    }
+    #[test]
+    fn test_limits(){
+        let limits = Limits { max_request_line: 64, max_header_size: 64, max_body_size: 16, ..Limits::default() };
+        let cases = [
+            (format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(100)), "414 URI Too Long"),
+            (format!("GET / HTTP/1.1\r\nX-Padding: {}\r\n\r\n", "a".repeat(100)), "431 Request Header Fields Too Large"),
+        ];
+        for (request, expected) in cases {
+            let (mut connection, mut client) = connection_pair(limits, RedirectTable::default());
+            let (status, output) = exchange(&mut connection, &mut client, request.as_bytes());
+            assert_eq!(status, ConnectionStatus::Draining, "{expected}");
+            assert!(output.starts_with(&format!("HTTP/1.1 {expected}\r\n")), "{output}");
+        }
+    }
+    #[test]
+    fn test_body_too_large_closes_gracefully(){
+        let limits = Limits { max_body_size: 16, ..Limits::default() };
+        let (mut connection, mut client) = connection_pair(limits, RedirectTable::default());
+        let request = format!("POST /form HTTP/1.1\r\nContent-Length: 1000\r\n\r\n{}", "a".repeat(100));
+        let (status, output) = exchange(&mut connection, &mut client, request.as_bytes());
+        assert_eq!(status, ConnectionStatus::Draining);
+        assert!(output.starts_with("HTTP/1.1 413 Content Too Large\r\n"));
+        assert!(output.contains("Connection: close\r\n"));
+
+        // the rest of the body is read and dropped, one read per pass, until the client is done
+        client.write_all(&[b'a'; 900]).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        let deadline = Instant::now() + CLOSE_DRAIN_TIME;
+        while drain(&mut connection.stream, deadline) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(Instant::now() < deadline);
+    }
+    #[test]
+    fn test_expect_continue(){
+        let (mut connection, mut client) = connection_pair(Limits::default(), RedirectTable::default());
+        let head = b"POST /form HTTP/1.1\r\nContent-Type: application/x-www-form-urlencoded\r\nContent-Length: 13\r\nExpect: 100-continue\r\n\r\n";
+        let (status, output) = exchange(&mut connection, &mut client, head);
+        assert_eq!(status, ConnectionStatus::Open);
+        assert_eq!(output, "HTTP/1.1 100 Continue\r\n\r\n");
+
+        let (status, output) = exchange(&mut connection, &mut client, b"name=ana&id=7");
+        assert_eq!(status, ConnectionStatus::Handled);
+        assert!(output.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(output.ends_with("\r\n\r\nid: 7\nname: ana\n"));
+
+        // a request routing rejects gets its final answer without sending the body
+        let (mut connection, mut client) = connection_pair(Limits::default(), RedirectTable::default());
+        let head = b"POST /index.html HTTP/1.1\r\nContent-Length: 13\r\nExpect: 100-continue\r\n\r\n";
+        let (status, output) = exchange(&mut connection, &mut client, head);
+        assert_eq!(status, ConnectionStatus::Draining);
+        assert!(output.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+        assert!(!output.contains("100 Continue"));
+    }
+    #[test]
+    fn test_redirect_before_routing(){
+        let redirects = RedirectTable::parse("/index.html /home 308\n").unwrap();
+        let (mut connection, mut client) = connection_pair(Limits::default(), redirects);
+        let (status, output) = exchange(&mut connection, &mut client, b"GET /index.html?a=1 HTTP/1.1\r\n\r\n");
+        assert_eq!(status, ConnectionStatus::Handled);
+        assert!(output.starts_with("HTTP/1.1 308 Permanent Redirect\r\n"));
+        assert!(output.contains("Location: /home?a=1\r\n"));
+    }
+
+    fn connection_headers(req: Option<&str>, mut response: HttpResponse)->(bool, HeaderMap){
+        let req = req.map(|req| HttpRequest::parse(req.as_bytes()).unwrap());
+        if let Some(req) = &req {
+            response.set_version(&req.version);
+        }
+        let keep_alive = set_connection_headers(&mut response, req.as_ref());
+        (keep_alive, response.headers().clone())
+    }
+
+    #[test]
+    fn test_keep_alive(){
+        let (keep_alive, headers) = connection_headers(Some("GET / HTTP/1.1\r\n\r\n"), HttpResponse::ok());
+        assert!(keep_alive);
+        assert!(!headers.contains_key("Connection"));
+
+        let (keep_alive, headers) = connection_headers(Some("GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n"), HttpResponse::ok());
+        assert!(keep_alive);
+        assert_eq!(headers.get("Connection"), Some("keep-alive"));
+        assert_eq!(headers.get("Keep-Alive"), Some("timeout=5"));
+
+        // a stream is chunked on HTTP/1.1, so the connection stays usable
+        let stream = || HttpResponse::builder().body(Body::stream(Vec::new()));
+        let (keep_alive, _) = connection_headers(Some("GET / HTTP/1.1\r\n\r\n"), stream());
+        assert!(keep_alive);
+    }
+    #[test]
+    fn test_connection_close(){
+        let (keep_alive, headers) = connection_headers(Some("GET / HTTP/1.0\r\n\r\n"), HttpResponse::ok());
+        assert!(!keep_alive);
+        assert_eq!(headers.get("Connection"), Some("close"));
+
+        let (keep_alive, headers) = connection_headers(Some("GET / HTTP/1.1\r\nConnection: close\r\n\r\n"), HttpResponse::ok());
+        assert!(!keep_alive);
+        assert_eq!(headers.get("Connection"), Some("close"));
+
+        // on HTTP/1.0 a body without a length ends with the connection
+        let stream = HttpResponse::builder().body(Body::stream(Vec::new()));
+        let (keep_alive, headers) = connection_headers(Some("GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n"), stream);
+        assert!(!keep_alive);
+        assert_eq!(headers.get("Connection"), Some("close"));
+        assert!(!headers.contains_key("Keep-Alive"));
+
+        let forced = HttpResponse::builder().header("Connection", "close").header("Keep-Alive", "timeout=60").build();
+        let (keep_alive, headers) = connection_headers(Some("GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n"), forced);
+        assert!(!keep_alive);
+        assert_eq!(headers.get("Connection"), Some("close"));
+        assert!(!headers.contains_key("Keep-Alive"));
+
+        let (keep_alive, headers) = connection_headers(None, HttpResponse::ok());
+        assert!(!keep_alive);
+        assert_eq!(headers.get("Connection"), Some("close"));
+    }
+}
@@ -1,6 +1,6 @@
-use std::{net::TcpStream, collections::{HashMap, LinkedList}, sync::{Mutex, Arc}, io::Write, slice::Iter};
+use std::{net::TcpStream, collections::{HashMap, LinkedList}, sync::Arc};
 use sha1::{Sha1, Digest};
-use http::{http_request::HttpRequest, http_response::HttpResponse};
+use http::{header_map::HeaderMap, http_request::HttpRequest, http_response::HttpResponse, status_code::StatusCode};
 use base64::{Engine as _, engine::general_purpose};
 
 use crate::server::Connection;
@@ -31,48 +31,22 @@ impl WebSocketConnections{
         Some(connection)
     }
 
-    pub fn send_to_all(&mut self, buf: &[u8]){
-        for connection in self.connections_list.iter_mut(){
-        
-            let connection = Arc::get_mut(connection);
-            let connection = match connection {
-                None=>continue,
-                Some(connection)=>connection
-            };
-            let _ = connection.write(buf);
-        }
-    }
-
 }
 
 
-fn validate_header(key: &str, match_value: &str, headers: &HashMap<String, String>)->bool{
-    match headers.get(key) {
-        None=>false,
-        Some(value)=>value.as_str() == match_value
-    }
-}
+fn validate_upgrade_headers(headers: &HeaderMap)->bool{
+    let is_websocket = headers
+        .get("Upgrade")
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
 
-fn validate_upgrade_headers(headers: &HashMap<String, String>)->bool{
-    
-    let validate_pairs = [
-            ("Upgrade", "websocket"),
-            ("Connection", "Upgrade"),
-            ("Sec-WebSocket-Version", "13")
-        ];
-        
-    for (key, value) in validate_pairs.iter(){
-        if !validate_header(key, value, headers){
-            return false
-        }
-    }
-        
-    true
+    is_websocket
+        && headers.contains_token("Connection", "Upgrade")
+        && headers.get("Sec-WebSocket-Version") == Some("13")
 }
 
 pub fn handle_web_socket_upgrade(req: &HttpRequest, stream: &mut TcpStream)->Result<(), &'static str>{
     let headers = &req.headers;
-    if !validate_upgrade_headers(&headers) {
+    if !validate_upgrade_headers(headers) {
         return Err("Not ws upgrade");
     }
     let sec_web_socket_key = match headers.get("Sec-WebSocket-Key") {
@@ -80,22 +54,22 @@ pub fn handle_web_socket_upgrade(req: &HttpRequest, stream: &mut TcpStream)->Res
         None=>return Err("No sec key in headers")
     };
 
-    let mut response_headers = HashMap::new();
+    let mut response_headers = HeaderMap::new();
     response_headers.insert("Upgrade", "websocket".to_string());
     response_headers.insert("Connection", "Upgrade".to_string());
     response_headers.insert("Sec-WebSocket-Accept", get_web_socket_accept_key(sec_web_socket_key));
 
-    let mut response =  HttpResponse::new("101", Some(response_headers), None);
+    let mut response =  HttpResponse::new(StatusCode::SWITCHING_PROTOCOLS, Some(response_headers), None);
     let result = response.send_response(stream);    
-    if let Ok(_) = result {
+    if result.is_ok() {
         return Ok(())
     }
 
-    return Err("Something went wrong while writing to TCP stream")
+    Err("Something went wrong while writing to TCP stream")
 }
     
 
-pub fn get_web_socket_accept_key(request_key: &String)->String{
+pub fn get_web_socket_accept_key(request_key: &str)->String{
     let ws_uid = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
     let magic_string = format!("{request_key}{ws_uid}");
     let mut hasher = Sha1::new();
@@ -159,7 +133,7 @@ pub fn read_web_socket_message(buffer: &[u8])->Option<()>{
     let unmasked_payload = masked_payload.enumerate().map(|(i, byte)|{
         let mask_index = i % 4;
         let mask = masking_key_bytes[mask_index];
-        return byte ^ mask;
+        byte ^ mask
     }).collect::<Vec<_>>();
 
     let payload = std::str::from_utf8(&unmasked_payload).unwrap();